@@ -1,4 +1,4 @@
 mkdir -p build
 cd build
-cmake .. -G Ninja -DCMAKE_EXPORT_COMPILE_COMMANDS=1
+cmake .. -G Ninja -DCMAKE_EXPORT_COMPILE_COMMANDS=1 "$@"
 cmake --build .
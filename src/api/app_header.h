@@ -0,0 +1,65 @@
+#ifndef APP_HEADER_H_
+#define APP_HEADER_H_
+
+#include <stdint.h>
+
+/*
+ * layout of the image header, shared with the applications. an application
+ * builds its header in with IMAGE_HEADER() instead of leaving every field to
+ * the packaging command line:
+ *
+ *     IMAGE_HEADER(.version = IMAGE_VERSION(1, 2, 3), .board = 7)
+ *
+ * the fields are named as in Image_Header_T, c++ wants them in that order.
+ * the application's linker script puts the section first, in front of the
+ * vector table:
+ *
+ *     .image_header : { KEEP(*(.image_header)) } >QSPI
+ *     .isr_vector ALIGN(0x400) : { KEEP(*(.isr_vector)) } >QSPI
+ *
+ * tools/image_header.py finds the magic at the start of the binary and fills
+ * in length, the metadata and header_crc, and entry_offset if it was left 0.
+ */
+
+#define IMAGE_HEADER_MAGIC  0x494D4149 /* "IAMI" */
+
+/* the vector table has to be aligned for VTOR, 166 vectors round up to 1 KiB */
+#define IMAGE_ENTRY_ALIGN   0x400
+
+/* tags of the metadata area, 0x80 and up are left to the application */
+#define IMAGE_TLV_BUILD_ID  0x01
+#define IMAGE_TLV_GIT_HASH  0x02
+#define IMAGE_TLV_CHANNEL   0x03
+#define IMAGE_TLV_CUSTOMER  0x04
+#define IMAGE_TLV_USER      0x80
+
+/* board field of images that run on any board */
+#define IMAGE_BOARD_ANY     0
+
+typedef struct {
+    uint32_t magic;
+    uint32_t version;           /* of the application, for the boot log */
+    uint32_t length;            /* bytes from the start of the slot, header included */
+    uint32_t entry_offset;      /* of the vector table from the start of the slot */
+    uint32_t board;             /* BOARD_ID the image is built for, IMAGE_BOARD_ANY for all */
+    uint32_t board_rev;         /* lowest BOARD_REV it runs on */
+    uint32_t flash_size;        /* bytes of external flash it needs at least, 0 for any */
+    uint32_t expiry;            /* unix time it stops being started at, 0 for never */
+    uint32_t tlv_len;           /* bytes of metadata right behind the header */
+    uint32_t tlv_crc;           /* crc32 of the metadata */
+    uint32_t header_crc;        /* crc32 of the fields above */
+} Image_Header_T;
+
+/* a version the boot log shows as 0xMMmmpppp */
+#define IMAGE_VERSION(major, minor, patch) \
+    ((uint32_t)(major) << 24 | (uint32_t)(minor) << 16 | (uint32_t)(patch))
+
+/* the fields left out are the tool's, g++ would warn about each of them */
+#define IMAGE_HEADER(...) \
+    _Pragma("GCC diagnostic push") \
+    _Pragma("GCC diagnostic ignored \"-Wmissing-field-initializers\"") \
+    __attribute__((section(".image_header"), used)) \
+    const Image_Header_T image_header = {.magic = IMAGE_HEADER_MAGIC, __VA_ARGS__}; \
+    _Pragma("GCC diagnostic pop")
+
+#endif
@@ -0,0 +1,200 @@
+#include "boot_api.h"
+#include "w25q.h"
+#include "layout.h"
+#include "slots.h"
+#include "manifest.h"
+#include "irq.h"
+
+#if defined(BOOTLOADER_API) && defined(MPU_SANDBOX)
+#error "the bootloader api can't be called from the mpu sandbox"
+#endif
+
+#ifdef BOOTLOADER_API
+
+extern Flash_T flash;
+
+/* highest staged offset, only lives as long as the application session */
+__attribute__((section(".shared_ram"))) static uint32_t boot_api_staged;
+
+/*
+ * runs from the internal flash on behalf of the application. the application
+ * code and its vectors are in the memory mapped flash, so nothing may be
+ * fetched from there until memory map mode is restored.
+ */
+
+static bool boot_api_read(uint32_t N, uint32_t address, uint8_t *rbuffer)
+{
+    if (address > LAYOUT_MEMORY_SIZE - 1 || N > LAYOUT_MEMORY_SIZE - address) {
+        return false;
+    }
+
+    uint32_t primask = irq_lock();
+    bool ret = flash.read_memory_mapped(N, address, rbuffer);
+    irq_unlock(primask);
+
+    return ret;
+}
+
+/* the source buffer of a write must not vanish with the window */
+static bool boot_api_in_window(const uint8_t *buffer, uint32_t N)
+{
+    return (uint32_t)buffer < LAYOUT_XIP_BASE + LAYOUT_MEMORY_SIZE && (uint32_t)buffer + N > LAYOUT_XIP_BASE;
+}
+
+/* raw writes may hit the metadata behind the back of the cached copies */
+static void boot_api_invalidate(uint32_t address, uint32_t N)
+{
+    if (Metadata_T::overlaps(address, N)) {
+        slot_state_invalidate();
+        manifest_invalidate();
+    }
+}
+
+/* the running application is in the primary slot, it is left to stage_image */
+static bool boot_api_write(uint32_t N, uint32_t address, const uint8_t *sbuffer)
+{
+    if (address > LAYOUT_MEMORY_SIZE - 1 || N > LAYOUT_MEMORY_SIZE - address) {
+        return false;
+    }
+
+    if (Primary_Slot_T::overlaps(address, N)) {
+        return false;
+    }
+
+    if (boot_api_in_window(sbuffer, N)) {
+        return false;
+    }
+
+    uint32_t primask = irq_lock();
+
+    boot_api_invalidate(address, N);
+
+    flash.memory_unmap();
+    bool ret = flash.write_N_bytes(N, address, (uint8_t *)sbuffer);
+    flash.memory_map();
+
+    irq_unlock(primask);
+    return ret;
+}
+
+static bool boot_api_erase(uint32_t start, uint32_t end)
+{
+    if (start > end || end > LAYOUT_MEMORY_SIZE - 1) {
+        return false;
+    }
+
+    if (Primary_Slot_T::overlaps(start, end - start + 1)) {
+        return false;
+    }
+
+    uint32_t primask = irq_lock();
+
+    boot_api_invalidate(start, end - start + 1);
+
+    flash.memory_unmap();
+    bool ret = flash.sector_erase(start, end);
+    flash.memory_map();
+
+    irq_unlock(primask);
+    return ret;
+}
+
+static void boot_api_get_geometry(Boot_Api_Geometry_T *geometry)
+{
+    geometry->size = LAYOUT_MEMORY_SIZE;
+    geometry->sector_size = LAYOUT_SECTOR_SIZE;
+    geometry->page_size = 256;
+}
+
+static bool boot_api_stage_image(uint32_t slot, uint32_t offset, const uint8_t *data, uint32_t N)
+{
+    bool ret = true;
+
+    if (slot != BOOT_API_SLOT_SECONDARY || !Secondary_Slot_T::contains(Secondary_Slot_T::base + offset, N)) {
+        return false;
+    }
+
+    if (boot_api_in_window(data, N)) {
+        return false;
+    }
+
+    uint32_t address = Secondary_Slot_T::base + offset;
+    uint32_t first = (address + LAYOUT_SECTOR_SIZE - 1) / LAYOUT_SECTOR_SIZE * LAYOUT_SECTOR_SIZE;
+
+    uint32_t primask = irq_lock();
+
+    flash.memory_unmap();
+
+    //every sector starting inside this write
+    for (uint32_t sector = first; ret && sector < address + N; sector += LAYOUT_SECTOR_SIZE) {
+        ret = flash.sector_erase(sector, sector);
+    }
+
+    if (ret) {
+        ret = flash.write_N_bytes(N, address, (uint8_t *)data);
+    }
+
+    flash.memory_map();
+
+    irq_unlock(primask);
+
+    if (ret && offset + N > boot_api_staged) {
+        boot_api_staged = offset + N;
+    }
+
+    return ret;
+}
+
+static bool boot_api_mark_pending(uint32_t slot)
+{
+    if (slot != BOOT_API_SLOT_SECONDARY || boot_api_staged == 0) {
+        return false;
+    }
+
+    uint32_t size = (boot_api_staged + LAYOUT_SECTOR_SIZE - 1) / LAYOUT_SECTOR_SIZE * LAYOUT_SECTOR_SIZE;
+
+    uint32_t primask = irq_lock();
+
+    flash.memory_unmap();
+    bool ret = slot_state_write(slot, size, SLOT_TRIAL_NONE) == ERR_OK;
+    flash.memory_map();
+
+    irq_unlock(primask);
+    return ret;
+}
+
+static bool boot_api_confirm(void)
+{
+    uint32_t primask = irq_lock();
+
+    flash.memory_unmap();
+    Error_T error = slot_confirm();
+    flash.memory_map();
+
+    irq_unlock(primask);
+
+    boot_log(BOOT_LOG_CONFIRM, error, 0);
+    return error == ERR_OK;
+}
+
+__attribute__((used, section(".api_table")))
+static const Boot_Api_T boot_api = {
+    BOOT_API_MAGIC,
+    BOOT_API_VERSION,
+    boot_api_read,
+    boot_api_write,
+    boot_api_erase,
+    boot_api_get_geometry,
+    boot_api_stage_image,
+    boot_api_mark_pending,
+    boot_log_get,
+    boot_api_confirm,
+};
+
+#else
+
+/* keeps the slot in the internal flash taken, applications see no api */
+__attribute__((used, section(".api_table")))
+static const Boot_Api_T boot_api = {};
+
+#endif
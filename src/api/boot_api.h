@@ -0,0 +1,84 @@
+#ifndef BOOT_API_H_
+#define BOOT_API_H_
+
+#include <stdbool.h>
+#include <stdint.h>
+#include "layout_map.h"
+#include "boot_log.h"
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * flash primitives exported by the bootloader to the applications it boots.
+ * the table lives at a fixed address in the internal flash; entries are only
+ * ever appended, so an application checks the magic and that version is at
+ * least the one that introduced the entries it calls.
+ *
+ * the calls touching the flash run with interrupts masked: the application
+ * executes from the flash, which writes and erases take out of memory mapped
+ * mode for the duration of a call. get_geometry and get_log only return
+ * data. addresses are offsets from the start of the external flash.
+ *
+ * write and erase refuse the primary slot, which holds the running
+ * application; updates go through stage_image. no buffer passed in may point
+ * into the memory mapped window at 0x90000000, constant data has to be
+ * copied to ram first.
+ *
+ * the calls run at the caller's privilege level, so they are not available
+ * to an application in the MPU_SANDBOX. such a build leaves the table
+ * empty, with a magic of 0.
+ */
+
+#define BOOT_API_MAGIC      0x424D4149 /* "IAMB" */
+#define BOOT_API_VERSION    4
+
+#define BOOT_API_SLOT_PRIMARY   0
+#define BOOT_API_SLOT_SECONDARY 1
+
+typedef struct {
+    uint32_t size;
+    uint32_t sector_size;
+    uint32_t page_size;
+} Boot_Api_Geometry_T;
+
+typedef struct {
+    uint32_t magic;
+    uint32_t version;
+
+    /* version 1 */
+    bool (*read)(uint32_t N, uint32_t address, uint8_t *rbuffer);
+    bool (*write)(uint32_t N, uint32_t address, const uint8_t *sbuffer);
+    bool (*erase)(uint32_t start, uint32_t end);
+    void (*get_geometry)(Boot_Api_Geometry_T *geometry);
+
+    /*
+     * version 2: updates downloaded by the application. stage_image writes
+     * at an offset into the secondary slot, starting at 0 and going up; a
+     * sector is erased when the write covering its first byte arrives.
+     * mark_pending has the bootloader swap everything staged so far into the
+     * primary slot on the next boot, the image it replaces goes to the
+     * secondary slot.
+     */
+    bool (*stage_image)(uint32_t slot, uint32_t offset, const uint8_t *data, uint32_t N);
+    bool (*mark_pending)(uint32_t slot);
+
+    /* version 3: the bootloader event log, see boot_log.h */
+    const Boot_Log_T *(*get_log)(void);
+
+    /*
+     * version 4: an installed image is started once on trial and swapped
+     * back out on the next boot unless it calls confirm first, once it
+     * knows it works. returns false if the confirmation couldn't be stored.
+     */
+    bool (*confirm)(void);
+} Boot_Api_T;
+
+#define BOOT_API ((const Boot_Api_T *)LAYOUT_API_TABLE)
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,33 @@
+#include "boot_log.h"
+#include "stm32h7xx_hal.h"
+
+static Boot_Log_T boot_log_ring __attribute__((section(".noinit")));
+
+/* keeps the entries of earlier runs unless the ring is garbage, i.e. after power on */
+void boot_log_init(void)
+{
+    if (boot_log_ring.magic == BOOT_LOG_MAGIC && boot_log_ring.head < BOOT_LOG_ENTRIES)
+        return;
+
+    boot_log_ring.magic = BOOT_LOG_MAGIC;
+    boot_log_ring.head = 0;
+    boot_log_ring.count = 0;
+}
+
+void boot_log(Boot_Log_Event_T event, uint16_t code, uint32_t arg)
+{
+    Boot_Log_Entry_T *entry = &boot_log_ring.entries[boot_log_ring.head];
+
+    entry->tick = HAL_GetTick();
+    entry->event = event;
+    entry->code = code;
+    entry->arg = arg;
+
+    boot_log_ring.head = (boot_log_ring.head + 1) % BOOT_LOG_ENTRIES;
+    boot_log_ring.count++;
+}
+
+const Boot_Log_T *boot_log_get(void)
+{
+    return &boot_log_ring;
+}
@@ -0,0 +1,60 @@
+#ifndef BOOT_LOG_H_
+#define BOOT_LOG_H_
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * events of the bootloader runs, kept in uninitialized shared ram across soft
+ * resets and readable by the application through the bootloader api, so it
+ * can forward them even if nobody watched the console during boot.
+ * codes are the ERR_ values from errors.h; events are only ever appended.
+ */
+
+#define BOOT_LOG_MAGIC      0x424C4F47 /* "BLOG" */
+#define BOOT_LOG_ENTRIES    32
+
+typedef enum {
+    BOOT_LOG_BOOT = 1,          /* arg: RCC_RSR reset flags */
+    BOOT_LOG_CALIBRATION,       /* arg: qspi clock in MHz */
+    BOOT_LOG_TEMPERATURE,       /* arg: die temperature in C, recalibration follows */
+    BOOT_LOG_INSTALL,           /* arg: bytes installed, 0 if the image was installed already */
+    BOOT_LOG_UPLOAD,            /* arg: bytes loaded over the console */
+    BOOT_LOG_SELF_CHECK,        /* arg: crc computed over the bootloader image */
+    BOOT_LOG_PANIC,             /* arg: caller of panic() or the faulting pc */
+    BOOT_LOG_SESSION_IDLE,      /* arg: ms without input before the session was closed */
+    BOOT_LOG_JUMP,              /* arg: reset vector of the application, code: the vector check */
+    BOOT_LOG_IMAGE,             /* arg: image version, code: the header check */
+    BOOT_LOG_TRIAL,             /* arg: bytes the installed image was swapped in with */
+    BOOT_LOG_REVERT,            /* arg: bytes swapped back after an unconfirmed trial */
+    BOOT_LOG_CONFIRM,           /* code: storing the confirmation */
+    BOOT_LOG_DECISION,          /* arg: low bytes of slot action | trial << 8 | pending << 16 | boot << 24,
+                                   code: why it stays in the bootloader */
+} Boot_Log_Event_T;
+
+typedef struct {
+    uint32_t tick;              /* HAL_GetTick() of the run that logged it */
+    uint16_t event;
+    uint16_t code;
+    uint32_t arg;
+} Boot_Log_Entry_T;
+
+typedef struct {
+    uint32_t magic;
+    uint32_t head;              /* next entry to be written */
+    uint32_t count;             /* entries ever written, the ring keeps the last BOOT_LOG_ENTRIES */
+    Boot_Log_Entry_T entries[BOOT_LOG_ENTRIES];
+} Boot_Log_T;
+
+void boot_log_init(void);
+void boot_log(Boot_Log_Event_T event, uint16_t code, uint32_t arg);
+const Boot_Log_T *boot_log_get(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
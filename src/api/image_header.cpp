@@ -0,0 +1,117 @@
+#include "image_header.h"
+#include "layout.h"
+#include "crc32.h"
+#include "w25q.h"
+#include "rtc.h"
+
+extern Flash_T flash;
+
+/**
+ * @brief   check an image header, of the primary slot before a jump or of the
+ *          secondary slot before an install
+ * @param   header  the header as decoded from the slot
+ * @retval  ERR_OK if the vector table it points to can be looked at
+ */
+Error_T image_header_check(const Image_Header_T *header)
+{
+    uint8_t raw[IMAGE_HEADER_SIZE];
+    Crc32_T crc;
+
+    if (header->magic != IMAGE_HEADER_MAGIC)
+        return ERR_IMAGE_MAGIC;
+
+    image_header_encode(raw, header);
+    crc.update(raw, offsetof(Image_Header_T, header_crc));
+    if (crc.finalize() != header->header_crc)
+        return ERR_IMAGE_HEADER_CRC;
+
+    if (header->length > Primary_Slot_T::len)
+        return ERR_IMAGE_LENGTH;
+
+    if (header->tlv_len > IMAGE_TLV_MAX)
+        return ERR_IMAGE_TLV;
+
+    /* room for the initial stack pointer and the reset vector at least */
+    if (header->entry_offset < IMAGE_HEADER_SIZE + header->tlv_len || header->entry_offset % IMAGE_ENTRY_ALIGN != 0 ||
+        header->entry_offset > header->length || header->length - header->entry_offset < 8)
+        return ERR_IMAGE_ENTRY;
+
+    return ERR_OK;
+}
+
+/**
+ * @brief   check that an image is built for this hardware
+ * @param   header  passed image_header_check() already
+ * @param   target  this board, see image_target()
+ * @retval  ERR_OK, ERR_IMAGE_BOARD, ERR_IMAGE_FLASH_SIZE or ERR_IMAGE_EXPIRED
+ */
+Error_T image_header_match(const Image_Header_T *header, const Image_Target_T *target)
+{
+    if (header->board != IMAGE_BOARD_ANY && header->board != target->board)
+        return ERR_IMAGE_BOARD;
+    if (header->board_rev > target->board_rev)
+        return ERR_IMAGE_BOARD;
+    if (header->flash_size > target->flash_size)
+        return ERR_IMAGE_FLASH_SIZE;
+    if (header->expiry != 0 && target->now != 0 && target->now >= header->expiry)
+        return ERR_IMAGE_EXPIRED;
+
+    return ERR_OK;
+}
+
+/**
+ * @brief   check the metadata area behind a header
+ * @param   header  passed image_header_check() already
+ * @param   area    tlv_len bytes read from right behind the header
+ * @retval  ERR_OK or ERR_IMAGE_TLV if the crc doesn't match or an entry
+ *          runs past the end of the area
+ */
+Error_T image_tlv_check(const Image_Header_T *header, const uint8_t *area)
+{
+    Crc32_T crc;
+
+    crc.update(area, header->tlv_len);
+    if (crc.finalize() != header->tlv_crc)
+        return ERR_IMAGE_TLV;
+
+    uint32_t offset = 0;
+    Image_Tlv_T tlv;
+
+    while (image_tlv_next(area, header->tlv_len, &offset, &tlv))
+        ;
+
+    return offset == header->tlv_len ? ERR_OK : ERR_IMAGE_TLV;
+}
+
+/**
+ * @brief   walk the metadata area
+ * @param   offset  0 for the first entry, moved past the entry returned
+ * @retval  false at the end of the area, or with offset left on an entry
+ *          that doesn't fit in it
+ */
+bool image_tlv_next(const uint8_t *area, uint32_t len, uint32_t *offset, Image_Tlv_T *tlv)
+{
+    if (*offset >= len || len - *offset < 2 || len - *offset - 2 < area[*offset + 1])
+        return false;
+
+    tlv->tag = area[*offset];
+    tlv->len = area[*offset + 1];
+    tlv->value = &area[*offset + 2];
+    *offset += 2 + tlv->len;
+    return true;
+}
+
+/*
+ * the flash size is what the chip reports, a board may be fitted with a bigger
+ * one. an unlocked board is on the bench and runs whatever it is given, and
+ * without a set rtc there is no telling whether an image has expired
+ */
+void image_target(Image_Target_T *target)
+{
+    target->board = BOARD_ID;
+    target->board_rev = BOARD_REV;
+    target->flash_size = flash.size();
+
+    if (!rtc_production_locked() || !rtc_unix_time(&target->now))
+        target->now = 0;
+}
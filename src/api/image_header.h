@@ -0,0 +1,104 @@
+#ifndef IMAGE_HEADER_H_
+#define IMAGE_HEADER_H_
+
+#include <stddef.h>
+#include <stdint.h>
+#include "le.h"
+#include "errors.h"
+#include "app_header.h"
+
+/*
+ * header at the start of the primary slot, in front of the application's
+ * vector table, laid out in app_header.h. written or completed by
+ * tools/image_header.py, which also pads the gap up to the vector table.
+ * the bootloader refuses to start an image whose magic or header crc don't
+ * match, or that was built for other hardware. a development build can carry
+ * an expiry date, past it a production locked board with its rtc set won't
+ * start the image any more.
+ *
+ * between the header and the vector table there is room for tagged user
+ * metadata (build id, git hash, ...). it is copied along with the image and
+ * listed by the info command, the application finds it right behind its
+ * header. each entry is a tag byte, a length byte and that many bytes of
+ * value.
+ */
+
+/*
+ * identity of the board the bootloader is built for, set through the
+ * BOARD_ID and BOARD_REV cmake variables. an image for another board is
+ * refused instead of being started on the wrong pinout.
+ */
+#ifndef BOARD_ID
+#define BOARD_ID            0
+#endif
+#ifndef BOARD_REV
+#define BOARD_REV           0
+#endif
+
+/* what an image is matched against */
+typedef struct {
+    uint32_t board;
+    uint32_t board_rev;
+    uint32_t flash_size;
+    uint32_t now;               /* unix time, 0 where expiry isn't enforced */
+} Image_Target_T;
+
+/* one metadata entry, value points into the area it was found in */
+typedef struct {
+    uint8_t tag;
+    uint8_t len;
+    const uint8_t *value;
+} Image_Tlv_T;
+
+/* bytes in the flash, the fields follow each other as words */
+#define IMAGE_HEADER_SIZE   44
+
+/* the metadata has to fit in front of the lowest entry offset */
+#define IMAGE_TLV_MAX       (IMAGE_ENTRY_ALIGN - IMAGE_HEADER_SIZE)
+
+static_assert(sizeof(Image_Header_T) == IMAGE_HEADER_SIZE, "image header has padding");
+static_assert(offsetof(Image_Header_T, length) == 8, "image header layout changed");
+static_assert(offsetof(Image_Header_T, entry_offset) == 12, "image header layout changed");
+static_assert(offsetof(Image_Header_T, board) == 16, "image header layout changed");
+static_assert(offsetof(Image_Header_T, flash_size) == 24, "image header layout changed");
+static_assert(offsetof(Image_Header_T, expiry) == 28, "image header layout changed");
+static_assert(offsetof(Image_Header_T, tlv_len) == 32, "image header layout changed");
+static_assert(offsetof(Image_Header_T, header_crc) == 40, "image header layout changed");
+
+static inline void image_header_decode(Image_Header_T *header, const uint8_t *raw)
+{
+    header->magic = le32_get(raw);
+    header->version = le32_get(raw + 4);
+    header->length = le32_get(raw + 8);
+    header->entry_offset = le32_get(raw + 12);
+    header->board = le32_get(raw + 16);
+    header->board_rev = le32_get(raw + 20);
+    header->flash_size = le32_get(raw + 24);
+    header->expiry = le32_get(raw + 28);
+    header->tlv_len = le32_get(raw + 32);
+    header->tlv_crc = le32_get(raw + 36);
+    header->header_crc = le32_get(raw + 40);
+}
+
+static inline void image_header_encode(uint8_t *raw, const Image_Header_T *header)
+{
+    le32_put(raw, header->magic);
+    le32_put(raw + 4, header->version);
+    le32_put(raw + 8, header->length);
+    le32_put(raw + 12, header->entry_offset);
+    le32_put(raw + 16, header->board);
+    le32_put(raw + 20, header->board_rev);
+    le32_put(raw + 24, header->flash_size);
+    le32_put(raw + 28, header->expiry);
+    le32_put(raw + 32, header->tlv_len);
+    le32_put(raw + 36, header->tlv_crc);
+    le32_put(raw + 40, header->header_crc);
+}
+
+Error_T image_header_check(const Image_Header_T *header);
+Error_T image_header_match(const Image_Header_T *header, const Image_Target_T *target);
+void image_target(Image_Target_T *target);
+Error_T image_tlv_check(const Image_Header_T *header, const uint8_t *area);
+bool image_tlv_next(const uint8_t *area, uint32_t len, uint32_t *offset, Image_Tlv_T *tlv);
+
+#endif
@@ -0,0 +1,115 @@
+#include "boot.h"
+#include "layout.h"
+#include "crc32.h"
+#include "boot_log.h"
+#include "bsp.h"
+#include "mpu.h"
+#include "slots.h"
+#include "w25q.h"
+#include "stm32h7xx_hal.h"
+#include <stddef.h>
+
+extern Flash_T flash;
+
+/* start of the internal flash image and its crc, patched in by tools/image_crc.py */
+extern "C" const uint8_t _svectors[];
+extern "C" const uint8_t _simage_crc[];
+
+__attribute__((section(".image_crc"), used)) static const uint32_t boot_image_crc = 0xFFFFFFFF;
+
+/**
+ * @brief   check the bootloader image in the internal flash against the crc
+ *          stored behind it at build time
+ * @param   crc     set to the crc computed now, for the boot log
+ * @retval  ERR_OK or ERR_SELF_CHECK_CRC
+ */
+Error_T boot_self_check(uint32_t *crc)
+{
+    Crc32_T image;
+
+    image.update(_svectors, _simage_crc - _svectors);
+    *crc = image.finalize();
+
+    return *crc == boot_image_crc ? ERR_OK : ERR_SELF_CHECK_CRC;
+}
+
+/*
+ * nothing of the bootloader's stack is used past the msp switch, so the last
+ * steps can't be left to the compiler
+ */
+__attribute__((noreturn)) static void boot_jump(uint32_t stack_pointer, uint32_t reset_vector, uint32_t control)
+{
+    __asm volatile("msr msp, %0\n"
+                   "msr control, %2\n"
+                   "isb\n"
+                   "bx %1\n"
+                   :
+                   : "r"(stack_pointer), "r"(reset_vector), "r"(control)
+                   : "memory");
+    __builtin_unreachable();
+}
+
+/**
+ * @brief   hand over to the application in the primary slot
+ * @retval  only returns if its image header fails image_header_check(),
+ *          image_header_match() or image_tlv_check(), or its vector table
+ *          fails boot_check_vectors()
+ * @note    the flash is left memory mapped and the vector table is taken
+ *          from the window. a freshly installed image is marked as started
+ *          and reverted on the next boot unless it confirms. with
+ *          MPU_SANDBOX the application starts unprivileged behind the
+ *          sandbox regions
+ */
+Error_T boot_application(void)
+{
+    /* an image that fails the checks below used its start as well */
+    Slot_State_T state;
+    if (slot_state_read(&state) == ERR_OK && state.trial == SLOT_TRIAL_INSTALLED)
+        boot_log(BOOT_LOG_TRIAL, slot_trial_start(), state.size);
+
+    flash.memory_map();
+
+    Image_Header_T header;
+
+    image_header_decode(&header, (const uint8_t *)(QSPI_BASE + Primary_Slot_T::base));
+
+    Image_Target_T target;
+    image_target(&target);
+
+    Error_T error = image_header_check(&header);
+    if (error == ERR_OK)
+        error = image_header_match(&header, &target);
+    if (error == ERR_OK)
+        error = image_tlv_check(&header, (const uint8_t *)(QSPI_BASE + Primary_Slot_T::base + IMAGE_HEADER_SIZE));
+    boot_log(BOOT_LOG_IMAGE, error, header.version);
+
+    if (error != ERR_OK) {
+        flash.memory_unmap();
+        return error;
+    }
+
+    const uint32_t *vectors = (const uint32_t *)(QSPI_BASE + Primary_Slot_T::base + header.entry_offset);
+    uint32_t stack_pointer = vectors[0];
+    uint32_t reset_vector = vectors[1];
+
+    Boot_Check_T check = boot_check_vectors(stack_pointer, reset_vector, header.length);
+    boot_log(BOOT_LOG_JUMP, check, reset_vector);
+
+    if (check != BOOT_CHECK_OK) {
+        flash.memory_unmap();
+        return (Error_T)check;
+    }
+
+    bsp_deinit();
+
+    SCB->VTOR = (uint32_t)vectors;
+    __DSB();
+    __ISB();
+
+#ifdef MPU_SANDBOX
+    mpu_sandbox_init();
+    boot_jump(stack_pointer, reset_vector, CONTROL_nPRIV_Msk);
+#else
+    boot_jump(stack_pointer, reset_vector, 0);
+#endif
+}
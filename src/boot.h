@@ -0,0 +1,21 @@
+#ifndef BOOT_H_
+#define BOOT_H_
+
+#include <stdint.h>
+#include "errors.h"
+#include "image_header.h"
+#include "boot_select.h"
+
+/* time for a host to speak up before the application is started */
+#ifndef BOOT_DELAY_MS
+#define BOOT_DELAY_MS   1000
+#endif
+
+/* led toggle period, faster while there is nothing the bootloader could start */
+#define BOOT_LED_MS         500
+#define BOOT_LED_SAFE_MS    100
+
+Error_T boot_self_check(uint32_t *crc);
+Error_T boot_application(void);
+
+#endif
@@ -0,0 +1,161 @@
+#include "boot_select.h"
+#include "layout.h"
+#include "le.h"
+#include "image_header.h"
+#include "w25q.h"
+#include "stm32h7xx_hal.h"
+
+extern Flash_T flash;
+
+/* the header and the largest metadata area that fits in front of the vectors */
+static uint8_t select_buffer[IMAGE_HEADER_SIZE + IMAGE_TLV_MAX];
+
+struct Ram_Region_T {
+    uint32_t base;
+    uint32_t len;
+};
+
+static const Ram_Region_T ram_regions[] = {
+    {D1_DTCMRAM_BASE, 128 * 1024},
+    {D1_AXISRAM_BASE, 512 * 1024},
+    {D2_AHBSRAM_BASE, 288 * 1024},
+    {D3_SRAM_BASE, LAYOUT_SHARED_RAM_BASE - D3_SRAM_BASE},
+};
+
+static bool boot_stack_in_ram(uint32_t stack_pointer)
+{
+    for (const Ram_Region_T &region : ram_regions) {
+        /* the stack grows down, so the initial value may sit right past the end */
+        if (stack_pointer > region.base && stack_pointer - region.base <= region.len) {
+            return true;
+        }
+    }
+
+    return false;
+}
+
+/**
+ * @brief   sanity check the two first words of the application vector table
+ * @param   stack_pointer   initial main stack pointer
+ * @param   reset_vector    address of the reset handler
+ * @param   image_len       bytes of the image from the start of the slot, as
+ *                          validated by image_header_check()
+ * @retval  BOOT_CHECK_OK if jumping to the application is safe
+ */
+Boot_Check_T boot_check_vectors(uint32_t stack_pointer, uint32_t reset_vector, uint32_t image_len)
+{
+    if (!boot_stack_in_ram(stack_pointer)) {
+        return BOOT_CHECK_STACK_OUT_OF_RAM;
+    }
+
+    if (stack_pointer & 0x3) {
+        return BOOT_CHECK_STACK_UNALIGNED;
+    }
+
+    if ((reset_vector & 0x1) == 0) {
+        return BOOT_CHECK_RESET_NOT_THUMB;
+    }
+
+    uint32_t entry = reset_vector & ~0x1UL;
+
+    if (entry < QSPI_BASE || !Primary_Slot_T::contains(entry - QSPI_BASE, 2)) {
+        return BOOT_CHECK_RESET_OUT_OF_SLOT;
+    }
+
+    /* past the end of the image is erased or left over from an older one */
+    if (image_len < 2 || entry - QSPI_BASE > image_len - 2) {
+        return BOOT_CHECK_RESET_OUT_OF_IMAGE;
+    }
+
+    return BOOT_CHECK_OK;
+}
+
+/* what boot_application() checks once the window is mapped, through indirect reads */
+static Error_T boot_select_primary(uint32_t *version)
+{
+    Image_Header_T header;
+    Image_Target_T target;
+
+    if (!flash.read_N_bytes(sizeof(select_buffer), Primary_Slot_T::base, select_buffer))
+        return ERR_FLASH_READ;
+
+    image_header_decode(&header, select_buffer);
+    image_target(&target);
+
+    Error_T error = image_header_check(&header);
+    if (error == ERR_OK)
+        error = image_header_match(&header, &target);
+    if (error == ERR_OK)
+        error = image_tlv_check(&header, select_buffer + IMAGE_HEADER_SIZE);
+    if (error != ERR_OK)
+        return error;
+
+    *version = header.version;
+
+    if (!flash.read_N_bytes(8, Primary_Slot_T::base + header.entry_offset, select_buffer))
+        return ERR_FLASH_READ;
+
+    return (Error_T)boot_check_vectors(le32_get(select_buffer), le32_get(select_buffer + 4), header.length);
+}
+
+static Error_T boot_select_secondary(uint32_t *version)
+{
+    Image_Header_T header;
+
+    if (!flash.read_N_bytes(IMAGE_HEADER_SIZE, Secondary_Slot_T::base, select_buffer))
+        return ERR_FLASH_READ;
+
+    image_header_decode(&header, select_buffer);
+
+    Error_T error = image_header_check(&header);
+    if (error == ERR_OK)
+        *version = header.version;
+
+    return error;
+}
+
+/**
+ * @brief   carry out what the slot state asks for and decide whether the
+ *          application in the primary slot is started
+ * @param   decision    filled in with everything the decision was made on
+ * @param   update      false to leave the slots as they are, for a
+ *                      bootloader that failed its self check
+ * @note    boot_application() runs the checks of the primary slot again
+ *          before the jump, this only decides whether to try
+ */
+void boot_decide(Boot_Decision_T *decision, bool update)
+{
+    Slot_State_T state;
+
+    decision->state = slot_state_read(&state);
+    if (decision->state != ERR_OK) {
+        state.pending = SLOT_NONE;
+        state.trial = SLOT_TRIAL_NONE;
+        state.size = 0;
+    }
+
+    decision->pending = state.pending;
+    decision->trial = state.trial;
+    decision->size = state.size;
+
+    decision->action = SLOT_ACTION_NONE;
+    decision->update = ERR_OK;
+    if (update)
+        decision->action = slot_update(&decision->update);
+
+    decision->primary_version = 0;
+    decision->primary = boot_select_primary(&decision->primary_version);
+
+    /* an installed image that can't be started has had its one start, the previous one goes back */
+    if (update && decision->primary != ERR_OK && slot_state_read(&state) == ERR_OK &&
+        state.trial == SLOT_TRIAL_INSTALLED && slot_trial_start() == ERR_OK) {
+        decision->action = slot_update(&decision->update);
+        decision->primary = boot_select_primary(&decision->primary_version);
+    }
+
+    decision->secondary_version = 0;
+    decision->secondary = boot_select_secondary(&decision->secondary_version);
+
+    /* a half swapped primary slot may well start with a valid header */
+    decision->boot = decision->action != SLOT_ACTION_FAILED && decision->primary == ERR_OK;
+}
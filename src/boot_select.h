@@ -0,0 +1,40 @@
+#ifndef BOOT_SELECT_H_
+#define BOOT_SELECT_H_
+
+#include <stdint.h>
+#include "errors.h"
+#include "slots.h"
+
+/* same numbers as the error codes, so a result can be reported as is */
+typedef enum {
+    BOOT_CHECK_OK = ERR_OK,
+    BOOT_CHECK_STACK_OUT_OF_RAM = ERR_BOOT_STACK_OUT_OF_RAM,
+    BOOT_CHECK_STACK_UNALIGNED = ERR_BOOT_STACK_UNALIGNED,
+    BOOT_CHECK_RESET_OUT_OF_SLOT = ERR_BOOT_RESET_OUT_OF_SLOT,
+    BOOT_CHECK_RESET_NOT_THUMB = ERR_BOOT_RESET_NOT_THUMB,
+    BOOT_CHECK_RESET_OUT_OF_IMAGE = ERR_BOOT_RESET_OUT_OF_IMAGE,
+} Boot_Check_T;
+
+/*
+ * everything a boot decided on, printed and logged every boot, so why a
+ * board started an image or stayed in the bootloader can be told from the
+ * field logs
+ */
+typedef struct {
+    Error_T state;              //reading the slot state
+    uint32_t pending;           //install request found, SLOT_NONE if none
+    uint32_t trial;             //trial found, SLOT_TRIAL_
+    uint32_t size;              //bytes of the request or the trial
+    Slot_Action_T action;       //what slot_update() made of them
+    Error_T update;             //and its error
+    Error_T primary;            //header and vector checks of the primary slot after the update
+    uint32_t primary_version;
+    Error_T secondary;          //header check of the secondary slot, the image swapped out or refused
+    uint32_t secondary_version;
+    bool boot;                  //start the application
+} Boot_Decision_T;
+
+Boot_Check_T boot_check_vectors(uint32_t stack_pointer, uint32_t reset_vector, uint32_t image_len);
+void boot_decide(Boot_Decision_T *decision, bool update);
+
+#endif
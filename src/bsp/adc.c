@@ -0,0 +1,76 @@
+#include "adc.h"
+#include "panic.h"
+#include "errors.h"
+
+/* the sensor needs at least 9us of sampling, 810.5 cycles at 30 MHz is 27us */
+#define ADC_TEMPSENSOR_SAMPLETIME ADC_SAMPLETIME_810CYCLES_5
+
+#define ADC_VREF_MV 3300
+
+/* emulators don't model adc3, the temperature then reads as unavailable */
+void adc_init(ADC_HandleTypeDef *adc)
+{
+#ifndef EMULATION
+    ADC_ChannelConfTypeDef channel = {0};
+
+    __HAL_RCC_ADC3_CLK_ENABLE();
+
+    /* hclk / 4 and the fixed / 2 in front of the adc core, 30 MHz */
+    adc->Instance = ADC3;
+    adc->Init.ClockPrescaler = ADC_CLOCK_SYNC_PCLK_DIV4;
+    adc->Init.Resolution = ADC_RESOLUTION_16B;
+    adc->Init.ScanConvMode = ADC_SCAN_DISABLE;
+    adc->Init.EOCSelection = ADC_EOC_SINGLE_CONV;
+    adc->Init.LowPowerAutoWait = DISABLE;
+    adc->Init.ContinuousConvMode = DISABLE;
+    adc->Init.NbrOfConversion = 1;
+    adc->Init.DiscontinuousConvMode = DISABLE;
+    adc->Init.ExternalTrigConv = ADC_SOFTWARE_START;
+    adc->Init.ExternalTrigConvEdge = ADC_EXTERNALTRIGCONVEDGE_NONE;
+    adc->Init.ConversionDataManagement = ADC_CONVERSIONDATA_DR;
+    adc->Init.Overrun = ADC_OVR_DATA_OVERWRITTEN;
+    adc->Init.LeftBitShift = ADC_LEFTBITSHIFT_NONE;
+    adc->Init.OversamplingMode = DISABLE;
+
+    if (HAL_ADC_Init(adc) != HAL_OK) {
+        panic(ERR_PANIC);
+    }
+
+    channel.Channel = ADC_CHANNEL_TEMPSENSOR;
+    channel.Rank = ADC_REGULAR_RANK_1;
+    channel.SamplingTime = ADC_TEMPSENSOR_SAMPLETIME;
+    channel.SingleDiff = ADC_SINGLE_ENDED;
+    channel.OffsetNumber = ADC_OFFSET_NONE;
+
+    if (HAL_ADC_ConfigChannel(adc, &channel) != HAL_OK) {
+        panic(ERR_PANIC);
+    }
+
+    /* a failed calibration only costs accuracy, the derating steps are coarse */
+    HAL_ADCEx_Calibration_Start(adc, ADC_CALIB_OFFSET, ADC_SINGLE_ENDED);
+#endif
+}
+
+/* false if the conversion didn't finish, e.g. under an emulator without an adc model */
+bool adc_read_temperature(ADC_HandleTypeDef *adc, int32_t *celsius)
+{
+    uint32_t raw;
+
+#ifdef EMULATION
+    return false;
+#endif
+
+    if (HAL_ADC_Start(adc) != HAL_OK)
+        return false;
+
+    if (HAL_ADC_PollForConversion(adc, 10) != HAL_OK) {
+        HAL_ADC_Stop(adc);
+        return false;
+    }
+
+    raw = HAL_ADC_GetValue(adc);
+    HAL_ADC_Stop(adc);
+
+    *celsius = __HAL_ADC_CALC_TEMPERATURE(ADC_VREF_MV, raw, ADC_RESOLUTION_16B);
+    return true;
+}
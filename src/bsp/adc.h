@@ -0,0 +1,18 @@
+#ifndef ADC_H_
+#define ADC_H_
+
+#include "stm32h7xx_hal.h"
+#include <stdbool.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+void adc_init(ADC_HandleTypeDef *adc);
+bool adc_read_temperature(ADC_HandleTypeDef *adc, int32_t *celsius);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
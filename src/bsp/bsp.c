@@ -1,13 +1,57 @@
 #include "bsp.h"
 #include "rcc.h"
 #include "gpio.h"
+#include "core.h"
+#include "irq.h"
+#include "cycles.h"
 
 #include "stm32h7xx_hal.h"
 
 void bsp_init(void)
 {
     HAL_Init();
+    core_hold_secondary();
     rcc_init();
+    cycles_init();
     gpio_init();
 }
 
+/*
+ * bring the core and the peripherals used by the bootloader back to a quiet
+ * state before handing over to the application. clocks, gpio and the quadspi
+ * are left alone, the application runs from the memory mapped flash.
+ */
+void bsp_deinit(void)
+{
+    irq_lock();
+
+    SysTick->CTRL = 0;
+    SysTick->LOAD = 0;
+    SysTick->VAL = 0;
+    SCB->ICSR = SCB_ICSR_PENDSTCLR_Msk;
+
+    for (uint32_t i = 0; i < sizeof(NVIC->ICER) / sizeof(NVIC->ICER[0]); i++) {
+        NVIC->ICER[i] = 0xFFFFFFFF;
+        NVIC->ICPR[i] = 0xFFFFFFFF;
+    }
+
+    /* a reset also stops any stream that is still running */
+    __HAL_RCC_DMA1_FORCE_RESET();
+    __HAL_RCC_DMA2_FORCE_RESET();
+    __HAL_RCC_MDMA_FORCE_RESET();
+    __HAL_RCC_BDMA_FORCE_RESET();
+    __HAL_RCC_USART1_FORCE_RESET();
+
+    __HAL_RCC_DMA1_RELEASE_RESET();
+    __HAL_RCC_DMA2_RELEASE_RESET();
+    __HAL_RCC_MDMA_RELEASE_RESET();
+    __HAL_RCC_BDMA_RELEASE_RESET();
+    __HAL_RCC_USART1_RELEASE_RESET();
+
+    __HAL_RCC_USART1_CLK_DISABLE();
+
+    /* the second core, if there is one, may start now */
+    core_release_secondary();
+
+    __enable_irq();
+}
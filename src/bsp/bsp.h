@@ -6,6 +6,7 @@ extern "C" {
 #endif
 
 void bsp_init(void);
+void bsp_deinit(void);
 
 #ifdef __cplusplus
 }
@@ -0,0 +1,37 @@
+#include "core.h"
+
+#include "stm32h7xx_hal.h"
+
+#if defined(DUAL_CORE) && defined(CORE_CM7)
+void core_hold_secondary(void)
+{
+    __HAL_RCC_HSEM_CLK_ENABLE();
+
+    /* nothing else touches the semaphores this early, the take can't fail */
+    HAL_HSEM_FastTake(CORE_HSEM_BOOT);
+}
+
+void core_release_secondary(void)
+{
+    HAL_HSEM_Release(CORE_HSEM_BOOT, 0);
+}
+
+/* false if the cm4 grabbed the quadspi, i.e. it doesn't follow the template */
+bool core_take_qspi(void)
+{
+    return HAL_HSEM_FastTake(CORE_HSEM_QSPI) == HAL_OK;
+}
+#else
+void core_hold_secondary(void)
+{
+}
+
+void core_release_secondary(void)
+{
+}
+
+bool core_take_qspi(void)
+{
+    return true;
+}
+#endif
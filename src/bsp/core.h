@@ -0,0 +1,29 @@
+#ifndef CORE_H_
+#define CORE_H_
+
+#include <stdbool.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * dual core parts (h745/h755, DUAL_CORE from the device header) boot both
+ * cores at once. the cm7 runs this bootloader and the cm4 firmware is expected
+ * to wait on CORE_HSEM_BOOT, following the cube dual core template, so it only
+ * starts once the bootloader has handed over. the cm7 owns the quadspi during
+ * boot and keeps it afterwards, the application runs from it.
+ * on single core parts all of this compiles to nothing.
+ */
+#define CORE_HSEM_BOOT 0
+#define CORE_HSEM_QSPI 1
+
+void core_hold_secondary(void);
+void core_release_secondary(void);
+bool core_take_qspi(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,64 @@
+#include "cycles.h"
+
+__attribute__((section(".shared_ram"))) static uint32_t cycles_per_us;
+
+/* after rcc_init(), the counter itself is started by the boot profiling */
+void cycles_init(void)
+{
+    cycles_per_us = SystemCoreClock / 1000000;
+
+    if (cycles_per_us == 0)
+        cycles_per_us = 1;
+}
+
+/* the application owns the dwt and may have stopped the counter, it is restarted then */
+uint32_t cycles_now(void)
+{
+    if ((DWT->CTRL & DWT_CTRL_CYCCNTENA_Msk) == 0) {
+        CoreDebug->DEMCR |= CoreDebug_DEMCR_TRCENA_Msk;
+        DWT->LAR = 0xC5ACCE55;
+        DWT->CTRL |= DWT_CTRL_CYCCNTENA_Msk;
+    }
+
+    return DWT->CYCCNT;
+}
+
+/* wraps after about 8.9 s at 480 MHz, longer waits have to be split up */
+uint32_t cycles_us_since(uint32_t start)
+{
+    return (cycles_now() - start) / cycles_per_us;
+}
+
+/* cycle count up to which the masked tick has been advanced, 0 while the tick runs */
+__attribute__((section(".shared_ram"))) static uint32_t cycles_tick_mark;
+
+/*
+ * overrides the hal's weak one. with interrupts masked, as in the bootloader
+ * api calls, the systick doesn't count and every hal timeout would wait
+ * forever, so the tick is advanced from the cycle counter instead. the first
+ * masked call only takes the mark, a timeout can't already be running then.
+ */
+uint32_t HAL_GetTick(void)
+{
+    if (__get_PRIMASK() == 0 || cycles_per_us == 0) {
+        cycles_tick_mark = 0;
+        return uwTick;
+    }
+
+    uint32_t now = cycles_now() | 1;
+    uint32_t cycles_per_ms = cycles_per_us * 1000;
+
+    if (cycles_tick_mark == 0) {
+        cycles_tick_mark = now;
+    } else if (now - cycles_tick_mark >= cycles_per_ms) {
+        uint32_t ms = (now - cycles_tick_mark) / cycles_per_ms;
+
+        uwTick += ms;
+        cycles_tick_mark += ms * cycles_per_ms;
+
+        if (cycles_tick_mark == 0)
+            cycles_tick_mark = 1;
+    }
+
+    return uwTick;
+}
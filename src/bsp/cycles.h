@@ -0,0 +1,25 @@
+#ifndef CYCLES_H_
+#define CYCLES_H_
+
+#include "stm32h7xx_hal.h"
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * timing on the dwt cycle counter, for waits that have to work without the
+ * tick, e.g. inside bootloader api calls. the conversion factor is taken at
+ * the bootloader's clock and kept in the shared ram, an application running
+ * the core slower only gets longer timeouts. HAL_GetTick() is taken over as
+ * well, to keep counting with interrupts masked.
+ */
+void cycles_init(void);
+uint32_t cycles_now(void);
+uint32_t cycles_us_since(uint32_t start);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
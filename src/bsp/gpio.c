@@ -1,4 +1,6 @@
 #include "gpio.h"
+#include "qspi.h"
+#include "irq.h"
 #include "stm32h7xx_hal.h"
 
 static void gpio_led_init(void);
@@ -45,7 +47,7 @@ static void gpio_usart1_init(void)
     gpio_usart1_config.Alternate = GPIO_AF4_USART1;
     HAL_GPIO_Init(GPIOB, &gpio_usart1_config);
 
-    HAL_NVIC_SetPriority(USART1_IRQn, 0, 0);
+    HAL_NVIC_SetPriority(USART1_IRQn, IRQ_PRIO_USART, 0);
     HAL_NVIC_EnableIRQ(USART1_IRQn);
 }
 
@@ -84,5 +86,18 @@ static void gpio_qspi_init(void)
     gpio_qspi_config.Speed = GPIO_SPEED_FREQ_LOW;
     gpio_qspi_config.Alternate = GPIO_AF10_QUADSPI;
     HAL_GPIO_Init(GPIOB, &gpio_qspi_config);
+
+#ifdef QSPI_FLASH_PWR_PORT
+    /* keep the flash off until qspi_init() powers it with the right delays */
+    HAL_GPIO_WritePin(QSPI_FLASH_PWR_PORT, QSPI_FLASH_PWR_PIN,
+                      QSPI_FLASH_PWR_ON == GPIO_PIN_SET ? GPIO_PIN_RESET : GPIO_PIN_SET);
+
+    gpio_qspi_config.Pin = QSPI_FLASH_PWR_PIN;
+    gpio_qspi_config.Mode = GPIO_MODE_OUTPUT_PP;
+    gpio_qspi_config.Pull = GPIO_NOPULL;
+    gpio_qspi_config.Speed = GPIO_SPEED_FREQ_LOW;
+    gpio_qspi_config.Alternate = 0;
+    HAL_GPIO_Init(QSPI_FLASH_PWR_PORT, &gpio_qspi_config);
+#endif
 }
 
@@ -0,0 +1,72 @@
+#ifndef IRQ_H_
+#define IRQ_H_
+
+#include "stm32h7xx_hal.h"
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * every interrupt priority of the bootloader, lower numbers preempt higher
+ * ones (NVIC_PRIORITYGROUP_4, no sub priorities).
+ *
+ * flash metadata commits run with BASEPRI at IRQ_PRIO_COMMIT, which masks
+ * that priority and everything less urgent. the tick stays above it so the
+ * hal timeouts keep counting inside a commit.
+ */
+#define IRQ_PRIO_TICK       0
+#define IRQ_PRIO_COMMIT     1
+#define IRQ_PRIO_USART      2
+
+#define IRQ_PRIO_LOWEST     ((1 << __NVIC_PRIO_BITS) - 1)
+
+#ifdef __cplusplus
+static_assert(TICK_INT_PRIORITY == IRQ_PRIO_TICK, "hal tick priority doesn't follow irq.h");
+static_assert(IRQ_PRIO_TICK < IRQ_PRIO_COMMIT, "the tick has to run during flash commits");
+static_assert(IRQ_PRIO_USART >= IRQ_PRIO_COMMIT, "a transport irq outranks the flash commit section");
+static_assert(IRQ_PRIO_USART <= IRQ_PRIO_LOWEST, "priority doesn't fit into the nvic");
+#else
+_Static_assert(TICK_INT_PRIORITY == IRQ_PRIO_TICK, "hal tick priority doesn't follow irq.h");
+_Static_assert(IRQ_PRIO_TICK < IRQ_PRIO_COMMIT, "the tick has to run during flash commits");
+_Static_assert(IRQ_PRIO_USART >= IRQ_PRIO_COMMIT, "a transport irq outranks the flash commit section");
+_Static_assert(IRQ_PRIO_USART <= IRQ_PRIO_LOWEST, "priority doesn't fit into the nvic");
+#endif
+
+/* around erase/program sequences of the metadata, nests */
+static inline uint32_t irq_commit_enter(void)
+{
+    uint32_t basepri = __get_BASEPRI();
+
+    __set_BASEPRI_MAX(IRQ_PRIO_COMMIT << (8 - __NVIC_PRIO_BITS));
+    return basepri;
+}
+
+static inline void irq_commit_exit(uint32_t basepri)
+{
+    __set_BASEPRI(basepri);
+}
+
+/*
+ * everything off, for code that runs while the xip window is unmapped on
+ * behalf of the application and for the jump. the tick stops as well, the
+ * hal timeouts then count on the cycle counter, see cycles.c
+ */
+static inline uint32_t irq_lock(void)
+{
+    uint32_t primask = __get_PRIMASK();
+
+    __disable_irq();
+    return primask;
+}
+
+static inline void irq_unlock(uint32_t primask)
+{
+    __set_PRIMASK(primask);
+}
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
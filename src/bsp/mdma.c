@@ -0,0 +1,101 @@
+#include "mdma.h"
+#include "cycles.h"
+
+#define MDMA_TIMEOUT_US     100000
+
+#define MDMA_FLAGS_ALL      (MDMA_CIFCR_CTEIF | MDMA_CIFCR_CCTCIF | MDMA_CIFCR_CBRTIF | MDMA_CIFCR_CBTIF | MDMA_CIFCR_CLTCIF)
+
+/*
+ * the channel is programmed directly and keeps no state in ram: it is also
+ * used from bootloader api calls, when the bootloader's .bss belongs to the
+ * application. words are only used when everything is aligned, bursts when
+ * everything is aligned to one of them
+ */
+static uint32_t mdma_copy_ctcr(uint32_t src, uint32_t dst, uint32_t len)
+{
+    uint32_t ctcr = MDMA_REQUEST_SW | MDMA_BLOCK_TRANSFER | MDMA_DATAALIGN_PACKENABLE |
+                    (127 << MDMA_CTCR_TLEN_Pos);
+
+    if (((src | dst | len) & 0x3) != 0)
+        return ctcr | MDMA_SRC_INC_BYTE | MDMA_DEST_INC_BYTE | MDMA_SRC_DATASIZE_BYTE | MDMA_DEST_DATASIZE_BYTE;
+
+    ctcr |= MDMA_SRC_INC_WORD | MDMA_DEST_INC_WORD | MDMA_SRC_DATASIZE_WORD | MDMA_DEST_DATASIZE_WORD;
+
+    if (((src | dst | len) & 0x3F) == 0)
+        ctcr |= MDMA_SOURCE_BURST_16BEATS | MDMA_DEST_BURST_16BEATS;
+
+    return ctcr;
+}
+
+/* the tcms are reached over the ahb port, everything else over axi */
+static uint32_t mdma_copy_ctbr(uint32_t dst)
+{
+    uint32_t region = dst & 0xFF000000;
+
+    return region == 0x20000000 || region == 0x00000000 ? MDMA_CTBR_DBUS : 0;
+}
+
+static bool mdma_copy_block(MDMA_Channel_TypeDef *channel, uint32_t src, uint32_t dst, uint32_t len)
+{
+    channel->CCR = MDMA_PRIORITY_HIGH;
+    channel->CIFCR = MDMA_FLAGS_ALL;
+    channel->CTCR = mdma_copy_ctcr(src, dst, len);
+    channel->CBNDTR = len;
+    channel->CSAR = src;
+    channel->CDAR = dst;
+    channel->CTBR = mdma_copy_ctbr(dst);
+    channel->CLAR = 0;
+    channel->CMAR = 0;
+    channel->CMDR = 0;
+
+    channel->CCR |= MDMA_CCR_EN;
+    channel->CCR |= MDMA_CCR_SWRQ;
+
+    uint32_t start = cycles_now();
+    bool ret = false;
+
+    while (cycles_us_since(start) < MDMA_TIMEOUT_US) {
+        uint32_t cisr = channel->CISR;
+
+        if (cisr & MDMA_CISR_TEIF)
+            break;
+        if (cisr & MDMA_CISR_CTCIF) {
+            ret = true;
+            break;
+        }
+    }
+
+    channel->CCR &= ~MDMA_CCR_EN;
+    channel->CIFCR = MDMA_FLAGS_ALL;
+    return ret;
+}
+
+/**
+ * @brief   copy len bytes from the memory mapped flash at offset to dst
+ * @retval  false if the mdma failed, the caller can still fall back to memcpy
+ * @note    the mdma doesn't go through the d-cache, so the destination lines
+ *          are written back before and dropped after the copy. the clock is
+ *          enabled for every copy, bsp_deinit() resets the mdma before the
+ *          application starts
+ */
+bool mdma_copy_from_xip(void *dst, uint32_t offset, uint32_t len)
+{
+    uint32_t src = QSPI_BASE + offset;
+
+    if (len == 0)
+        return true;
+
+    __HAL_RCC_MDMA_CLK_ENABLE();
+
+    SCB_CleanInvalidateDCache_by_Addr(dst, len);
+
+    for (uint32_t done = 0; done < len; done += MDMA_BLOCK_MAX) {
+        uint32_t chunk = len - done < MDMA_BLOCK_MAX ? len - done : MDMA_BLOCK_MAX;
+
+        if (!mdma_copy_block(MDMA_COPY_CHANNEL, src + done, (uint32_t)dst + done, chunk))
+            return false;
+    }
+
+    SCB_InvalidateDCache_by_Addr(dst, len);
+    return true;
+}
@@ -0,0 +1,26 @@
+#ifndef MDMA_H_
+#define MDMA_H_
+
+#include "stm32h7xx_hal.h"
+#include <stdbool.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * the last mdma channel is taken for bulk copies out of the memory mapped
+ * flash, applications calling the bootloader api must leave it alone
+ */
+#define MDMA_COPY_CHANNEL   MDMA_Channel15
+
+/* block length limit of one mdma block */
+#define MDMA_BLOCK_MAX      65536
+
+bool mdma_copy_from_xip(void *dst, uint32_t offset, uint32_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
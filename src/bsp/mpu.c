@@ -0,0 +1,93 @@
+#include "mpu.h"
+#include "layout_map.h"
+#include "stm32h7xx_hal.h"
+
+#if LAYOUT_MEMORY_SIZE != 0x800000 || LAYOUT_METADATA_LEN != 0x10000
+#error "mpu region sizes have to follow the layout"
+#endif
+
+#if LAYOUT_METADATA_BASE % LAYOUT_METADATA_LEN != 0
+#error "metadata partition has to be aligned to its size to be covered by one mpu region"
+#endif
+
+/*
+ * restrict what the application can do to the external flash. the memory
+ * mapped window is read only everywhere and the metadata partition can't be
+ * executed from. the flash and quadspi registers are privileged only, so once
+ * the application runs unprivileged it can't issue erase or program commands
+ * and all flash updates have to go through the bootloader. the remaining
+ * regions only give unprivileged code back the ram and peripherals it needs,
+ * as the default memory map is not available to it.
+ */
+void mpu_sandbox_init(void)
+{
+    MPU_Region_InitTypeDef region = {0};
+
+    HAL_MPU_Disable();
+
+    /* itcm and the internal flash, the bootloader api runs from the latter */
+    region.Enable = MPU_REGION_ENABLE;
+    region.Number = MPU_REGION_NUMBER0;
+    region.BaseAddress = 0x00000000;
+    region.Size = MPU_REGION_SIZE_512MB;
+    region.SubRegionDisable = 0x00;
+    region.TypeExtField = MPU_TEX_LEVEL0;
+    region.AccessPermission = MPU_REGION_FULL_ACCESS;
+    region.DisableExec = MPU_INSTRUCTION_ACCESS_ENABLE;
+    region.IsShareable = MPU_ACCESS_NOT_SHAREABLE;
+    region.IsCacheable = MPU_ACCESS_CACHEABLE;
+    region.IsBufferable = MPU_ACCESS_NOT_BUFFERABLE;
+    HAL_MPU_ConfigRegion(&region);
+
+    /* all of the sram */
+    region.Number = MPU_REGION_NUMBER1;
+    region.BaseAddress = D1_DTCMRAM_BASE;
+    HAL_MPU_ConfigRegion(&region);
+
+    /* peripherals */
+    region.Number = MPU_REGION_NUMBER2;
+    region.BaseAddress = PERIPH_BASE;
+    region.DisableExec = MPU_INSTRUCTION_ACCESS_DISABLE;
+    region.IsShareable = MPU_ACCESS_SHAREABLE;
+    region.IsCacheable = MPU_ACCESS_NOT_CACHEABLE;
+    region.IsBufferable = MPU_ACCESS_BUFFERABLE;
+    HAL_MPU_ConfigRegion(&region);
+
+    /* whole quadspi window, keeps speculative reads away from addresses past the chip */
+    region.Number = MPU_REGION_NUMBER3;
+    region.BaseAddress = QSPI_BASE;
+    region.Size = MPU_REGION_SIZE_256MB;
+    region.AccessPermission = MPU_REGION_NO_ACCESS;
+    region.IsBufferable = MPU_ACCESS_NOT_BUFFERABLE;
+    HAL_MPU_ConfigRegion(&region);
+
+    region.Number = MPU_REGION_NUMBER4;
+    region.Size = MPU_REGION_SIZE_8MB;
+    region.AccessPermission = MPU_REGION_PRIV_RO_URO;
+    region.DisableExec = MPU_INSTRUCTION_ACCESS_ENABLE;
+    region.IsShareable = MPU_ACCESS_NOT_SHAREABLE;
+    region.IsCacheable = MPU_ACCESS_CACHEABLE;
+    HAL_MPU_ConfigRegion(&region);
+
+    region.Number = MPU_REGION_NUMBER5;
+    region.BaseAddress = QSPI_BASE + LAYOUT_METADATA_BASE;
+    region.Size = MPU_REGION_SIZE_64KB;
+    region.DisableExec = MPU_INSTRUCTION_ACCESS_DISABLE;
+    HAL_MPU_ConfigRegion(&region);
+
+    /* flash and quadspi controllers */
+    region.Number = MPU_REGION_NUMBER6;
+    region.BaseAddress = FLASH_R_BASE;
+    region.Size = MPU_REGION_SIZE_4KB;
+    region.AccessPermission = MPU_REGION_PRIV_RW;
+    region.IsShareable = MPU_ACCESS_SHAREABLE;
+    region.IsCacheable = MPU_ACCESS_NOT_CACHEABLE;
+    region.IsBufferable = MPU_ACCESS_BUFFERABLE;
+    HAL_MPU_ConfigRegion(&region);
+
+    region.Number = MPU_REGION_NUMBER7;
+    region.BaseAddress = QSPI_R_BASE;
+    HAL_MPU_ConfigRegion(&region);
+
+    HAL_MPU_Enable(MPU_PRIVILEGED_DEFAULT);
+}
@@ -0,0 +1,14 @@
+#ifndef MPU_H_
+#define MPU_H_
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+void mpu_sandbox_init(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
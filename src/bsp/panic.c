@@ -0,0 +1,73 @@
+#include "panic.h"
+#include "irq.h"
+#include "boot_log.h"
+#include "errors.h"
+#include "stm32h7xx_hal.h"
+
+#ifdef PANIC_WIPE
+/* the ram the bootloader leaves to the application, see the dump regions */
+static void panic_wipe(void)
+{
+    __HAL_RCC_D2SRAM1_CLK_ENABLE();
+    __HAL_RCC_D2SRAM2_CLK_ENABLE();
+    __HAL_RCC_D2SRAM3_CLK_ENABLE();
+
+    for (volatile uint32_t *word = (uint32_t *)D1_AXISRAM_BASE; word < (uint32_t *)(D1_AXISRAM_BASE + 512 * 1024); word++)
+        *word = 0;
+    for (volatile uint32_t *word = (uint32_t *)D2_AHBSRAM_BASE; word < (uint32_t *)(D2_AHBSRAM_BASE + 288 * 1024); word++)
+        *word = 0;
+
+    SCB_CleanDCache();
+}
+#endif
+
+static void __attribute__((noreturn)) panic_at(uint16_t code, uint32_t address)
+{
+    irq_lock();
+
+    /* the log survives the reset, the entry shows where it happened */
+    boot_log_init();
+    boot_log(BOOT_LOG_PANIC, code, address);
+
+#if defined(PANIC_RESET) || defined(PANIC_WIPE)
+#ifdef PANIC_WIPE
+    panic_wipe();
+#endif
+    NVIC_SystemReset();
+#else
+    /* without a debugger the breakpoint would escalate into another fault */
+    if (CoreDebug->DHCSR & CoreDebug_DHCSR_C_DEBUGEN_Msk)
+        __BKPT(0);
+#endif
+
+    while (1);
+}
+
+/**
+ * @brief   stop after an unrecoverable error, as the build's policy says
+ * @param   code    ERR_ value for the boot log, ERR_PANIC if nothing fits
+ */
+void panic(uint16_t code)
+{
+    panic_at(code, (uint32_t)__builtin_return_address(0));
+}
+
+/* frame is the exception stack frame, the faulting pc is its seventh word */
+void panic_fault(uint32_t *frame)
+{
+    panic_at(ERR_FAULT, frame[6]);
+}
+
+__attribute__((naked)) void HardFault_Handler(void)
+{
+    __asm volatile(
+        "tst lr, #4\n"
+        "ite eq\n"
+        "mrseq r0, msp\n"
+        "mrsne r0, psp\n"
+        "b panic_fault\n");
+}
+
+void MemManage_Handler(void) __attribute__((alias("HardFault_Handler")));
+void BusFault_Handler(void) __attribute__((alias("HardFault_Handler")));
+void UsageFault_Handler(void) __attribute__((alias("HardFault_Handler")));
@@ -0,0 +1,23 @@
+#ifndef PANIC_H_
+#define PANIC_H_
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * what happens on an unrecoverable error is picked per build with
+ * -DPANIC_POLICY=halt|reset|wipe:
+ *   halt   stop, in a breakpoint if a debugger is attached (default)
+ *   reset  log to the boot log and reset, the bootloader comes back up
+ *   wipe   as reset, but clear the ram the application had first
+ */
+void panic(uint16_t code) __attribute__((noreturn));
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
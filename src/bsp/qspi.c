@@ -1,13 +1,22 @@
 #include "qspi.h"
+#include "panic.h"
+#include "errors.h"
+#include "core.h"
+#include "cycles.h"
+#include "layout_map.h"
 
 void qspi_init(QSPI_HandleTypeDef *qspi)
 {
+    if (!core_take_qspi()) {
+        panic(ERR_PANIC);
+    }
+
     RCC_PeriphCLKInitTypeDef PeriphClkInitStruct = {0};
     PeriphClkInitStruct.PeriphClockSelection = RCC_PERIPHCLK_QSPI;
     PeriphClkInitStruct.QspiClockSelection = RCC_QSPICLKSOURCE_D1HCLK;
 
     if (HAL_RCCEx_PeriphCLKConfig(&PeriphClkInitStruct) != HAL_OK) {
-        while (1);
+        panic(ERR_PANIC);
     }
 
     __HAL_RCC_QSPI_CLK_ENABLE();
@@ -16,13 +25,135 @@ void qspi_init(QSPI_HandleTypeDef *qspi)
     qspi->Init.ClockPrescaler = 2;
     qspi->Init.FifoThreshold = 4;
     qspi->Init.SampleShifting = QSPI_SAMPLE_SHIFTING_NONE;
-    qspi->Init.FlashSize = 1;
+    /* 2^(FSIZE + 1) bytes, the memory mapped window and indirect accesses end there */
+    qspi->Init.FlashSize = POSITION_VAL(LAYOUT_MEMORY_SIZE) - 1;
     qspi->Init.ChipSelectHighTime = QSPI_CS_HIGH_TIME_5_CYCLE;
     qspi->Init.ClockMode = QSPI_CLOCK_MODE_0;
     qspi->Init.FlashID = QSPI_FLASH_ID_1;
     qspi->Init.DualFlash = QSPI_DUALFLASH_DISABLE;
 
+#ifndef EMULATION
     if (HAL_QSPI_Init(qspi) != HAL_OK) {
-        while (1);
+        panic(ERR_PANIC);
     }
+#endif
+
+    qspi_flash_power_on();
+}
+
+/* the peripheral must not be busy, i.e. not in memory mapped mode */
+void qspi_set_prescaler(QSPI_HandleTypeDef *qspi, uint32_t prescaler)
+{
+    qspi->Init.ClockPrescaler = prescaler;
+    MODIFY_REG(qspi->Instance->CR, QUADSPI_CR_PRESCALER, prescaler << QUADSPI_CR_PRESCALER_Pos);
+}
+
+/* same restriction as qspi_set_prescaler */
+void qspi_set_sample_shift(QSPI_HandleTypeDef *qspi, uint32_t shift)
+{
+    qspi->Init.SampleShifting = shift;
+    MODIFY_REG(qspi->Instance->CR, QUADSPI_CR_SSHIFT, shift);
+}
+
+uint32_t qspi_clock_hz(QSPI_HandleTypeDef *qspi)
+{
+    return HAL_RCC_GetHCLKFreq() / (qspi->Init.ClockPrescaler + 1);
+}
+
+void qspi_flash_power_on(void)
+{
+#ifdef QSPI_FLASH_PWR_PORT
+    HAL_GPIO_WritePin(QSPI_FLASH_PWR_PORT, QSPI_FLASH_PWR_PIN, QSPI_FLASH_PWR_ON);
+    HAL_Delay(QSPI_FLASH_PWR_UP_MS);
+#endif
+}
+
+void qspi_flash_power_off(void)
+{
+#ifdef QSPI_FLASH_PWR_PORT
+    HAL_GPIO_WritePin(QSPI_FLASH_PWR_PORT, QSPI_FLASH_PWR_PIN,
+                      QSPI_FLASH_PWR_ON == GPIO_PIN_SET ? GPIO_PIN_RESET : GPIO_PIN_SET);
+    HAL_Delay(QSPI_FLASH_PWR_DOWN_MS);
+#endif
+}
+
+#ifdef QSPI_TRACE
+/* bootloader api calls are traced as well, after the application took over the .bss */
+__attribute__((section(".shared_ram"))) static Qspi_Trace_T qspi_trace[QSPI_TRACE_ENTRIES];
+__attribute__((section(".shared_ram"))) static uint32_t qspi_trace_head;
+__attribute__((section(".shared_ram"))) static uint32_t qspi_trace_total;
+__attribute__((section(".shared_ram"))) static uint32_t qspi_trace_start;
+
+static void qspi_trace_begin(QSPI_CommandTypeDef *cmd, uint32_t len)
+{
+    Qspi_Trace_T *entry = &qspi_trace[qspi_trace_head];
+
+    entry->instruction = cmd->Instruction;
+    entry->lines = ((cmd->InstructionMode >> QUADSPI_CCR_IMODE_Pos) & 0x3) << 4 |
+                   ((cmd->AddressMode >> QUADSPI_CCR_ADMODE_Pos) & 0x3) << 2 |
+                   ((cmd->DataMode >> QUADSPI_CCR_DMODE_Pos) & 0x3);
+    entry->dummy = cmd->DummyCycles;
+    entry->address = cmd->AddressMode == QSPI_ADDRESS_NONE ? 0 : cmd->Address;
+    entry->len = cmd->DataMode == QSPI_DATA_NONE ? 0 : len;
+
+    qspi_trace_head = (qspi_trace_head + 1) % QSPI_TRACE_ENTRIES;
+    qspi_trace_total++;
+    qspi_trace_start = cycles_now();
+}
+
+static void qspi_trace_end(HAL_StatusTypeDef status)
+{
+    Qspi_Trace_T *entry = &qspi_trace[(qspi_trace_head + QSPI_TRACE_ENTRIES - 1) % QSPI_TRACE_ENTRIES];
+
+    entry->cycles = cycles_now() - qspi_trace_start;
+    entry->status = status;
+}
+
+HAL_StatusTypeDef qspi_command(QSPI_HandleTypeDef *qspi, QSPI_CommandTypeDef *cmd, uint32_t timeout)
+{
+    qspi_trace_begin(cmd, cmd->NbData);
+
+    HAL_StatusTypeDef status = HAL_QSPI_Command(qspi, cmd, timeout);
+    qspi_trace_end(status);
+    return status;
+}
+
+HAL_StatusTypeDef qspi_transmit(QSPI_HandleTypeDef *qspi, uint8_t *data, uint32_t timeout)
+{
+    HAL_StatusTypeDef status = HAL_QSPI_Transmit(qspi, data, timeout);
+    qspi_trace_end(status);
+    return status;
+}
+
+HAL_StatusTypeDef qspi_receive(QSPI_HandleTypeDef *qspi, uint8_t *data, uint32_t timeout)
+{
+    HAL_StatusTypeDef status = HAL_QSPI_Receive(qspi, data, timeout);
+    qspi_trace_end(status);
+    return status;
+}
+
+/* one entry for the whole poll, it covers the instruction as well */
+HAL_StatusTypeDef qspi_autopolling(QSPI_HandleTypeDef *qspi, QSPI_CommandTypeDef *cmd,
+                                           QSPI_AutoPollingTypeDef *cfg, uint32_t timeout)
+{
+    qspi_trace_begin(cmd, cfg->StatusBytesSize);
+
+    HAL_StatusTypeDef status = HAL_QSPI_AutoPolling(qspi, cmd, cfg, timeout);
+    qspi_trace_end(status);
+    return status;
+}
+
+uint32_t qspi_trace_count(void)
+{
+    return qspi_trace_total < QSPI_TRACE_ENTRIES ? qspi_trace_total : QSPI_TRACE_ENTRIES;
+}
+
+/* age 0 is the newest transaction */
+const Qspi_Trace_T *qspi_trace_get(uint32_t age)
+{
+    if (age >= qspi_trace_count())
+        return NULL;
+
+    return &qspi_trace[(qspi_trace_head + QSPI_TRACE_ENTRIES - 1 - age) % QSPI_TRACE_ENTRIES];
 }
+#endif
@@ -7,7 +7,121 @@ extern "C" {
 
 #include "stm32h7xx_hal.h"
 
+/*
+ * boards gating the flash supply through a load switch define both
+ * QSPI_FLASH_PWR_PORT and QSPI_FLASH_PWR_PIN, e.g. -DQSPI_FLASH_PWR_PORT=GPIOD
+ */
+#ifndef QSPI_FLASH_PWR_ON
+#define QSPI_FLASH_PWR_ON GPIO_PIN_SET
+#endif
+
+/* covers tPUW of the w25q64, the chip ignores writes before that */
+#define QSPI_FLASH_PWR_UP_MS    10
+/* lets the supply discharge so the chip sees a proper power on reset */
+#define QSPI_FLASH_PWR_DOWN_MS  10
+
+/*
+ * the flash drivers go through these, so a QSPI_TRACE build can keep the last
+ * QSPI_TRACE_ENTRIES transactions. without it they are the plain hal calls.
+ */
+#ifdef QSPI_TRACE
+#define QSPI_TRACE_ENTRIES 32
+
+typedef struct {
+    uint8_t instruction;
+    uint8_t lines;          /* instruction, address and data lines, 2 bits each as in CCR */
+    uint8_t dummy;
+    uint8_t status;         /* HAL_StatusTypeDef of the last phase */
+    uint32_t address;
+    uint32_t len;
+    uint32_t cycles;        /* command through the end of the data phase */
+} Qspi_Trace_T;
+
+HAL_StatusTypeDef qspi_command(QSPI_HandleTypeDef *qspi, QSPI_CommandTypeDef *cmd, uint32_t timeout);
+HAL_StatusTypeDef qspi_transmit(QSPI_HandleTypeDef *qspi, uint8_t *data, uint32_t timeout);
+HAL_StatusTypeDef qspi_receive(QSPI_HandleTypeDef *qspi, uint8_t *data, uint32_t timeout);
+HAL_StatusTypeDef qspi_autopolling(QSPI_HandleTypeDef *qspi, QSPI_CommandTypeDef *cmd,
+                                   QSPI_AutoPollingTypeDef *cfg, uint32_t timeout);
+uint32_t qspi_trace_count(void);
+const Qspi_Trace_T *qspi_trace_get(uint32_t age);
+#else
+#define qspi_command(qspi, cmd, timeout)            HAL_QSPI_Command(qspi, cmd, timeout)
+#define qspi_transmit(qspi, data, timeout)          HAL_QSPI_Transmit(qspi, data, timeout)
+#define qspi_receive(qspi, data, timeout)           HAL_QSPI_Receive(qspi, data, timeout)
+#define qspi_autopolling(qspi, cmd, cfg, timeout)   HAL_QSPI_AutoPolling(qspi, cmd, cfg, timeout)
+#endif
+
+/*
+ * helpers filling in one phase of a QSPI_CommandTypeDef each. the command has
+ * to start out zeroed, phases that are never set stay off.
+ */
+typedef enum {
+    QSPI_LINES_NONE = 0,
+    QSPI_LINES_1 = 1,
+    QSPI_LINES_2 = 2,
+    QSPI_LINES_4 = 4,
+} Qspi_Lines_T;
+
+/* the HAL_QSPI modes are the CCR encoding shifted into place */
+static inline uint32_t qspi_lines_encoding(Qspi_Lines_T lines)
+{
+    switch (lines) {
+    case QSPI_LINES_1:
+        return 1;
+    case QSPI_LINES_2:
+        return 2;
+    case QSPI_LINES_4:
+        return 3;
+    default:
+        return 0;
+    }
+}
+
+static inline void qspi_cmd_instruction(QSPI_CommandTypeDef *cmd, uint8_t instruction, Qspi_Lines_T lines)
+{
+    cmd->Instruction = instruction;
+    cmd->InstructionMode = qspi_lines_encoding(lines) << QUADSPI_CCR_IMODE_Pos;
+}
+
+static inline void qspi_cmd_address(QSPI_CommandTypeDef *cmd, uint32_t address, Qspi_Lines_T lines)
+{
+    cmd->Address = address;
+    cmd->AddressSize = QSPI_ADDRESS_24_BITS;
+    cmd->AddressMode = qspi_lines_encoding(lines) << QUADSPI_CCR_ADMODE_Pos;
+}
+
+/* the M7-M0 byte of the fast read and continuous read sequences */
+static inline void qspi_cmd_mode_byte(QSPI_CommandTypeDef *cmd, uint8_t mode, Qspi_Lines_T lines)
+{
+    cmd->AlternateBytes = mode;
+    cmd->AlternateBytesSize = QSPI_ALTERNATE_BYTES_8_BITS;
+    cmd->AlternateByteMode = qspi_lines_encoding(lines) << QUADSPI_CCR_ABMODE_Pos;
+}
+
+/* clocks of a mode byte, for callers fitting it into a fixed wait */
+static inline uint32_t qspi_mode_byte_cycles(Qspi_Lines_T lines)
+{
+    return lines == QSPI_LINES_NONE ? 0 : 8 / lines;
+}
+
+/* the controller takes 0 to 31 */
+static inline void qspi_cmd_dummy(QSPI_CommandTypeDef *cmd, uint32_t cycles)
+{
+    cmd->DummyCycles = cycles > 31 ? 31 : cycles;
+}
+
+static inline void qspi_cmd_data(QSPI_CommandTypeDef *cmd, uint32_t N, Qspi_Lines_T lines)
+{
+    cmd->NbData = N;
+    cmd->DataMode = qspi_lines_encoding(lines) << QUADSPI_CCR_DMODE_Pos;
+}
+
 void qspi_init(QSPI_HandleTypeDef *qspi);
+void qspi_set_prescaler(QSPI_HandleTypeDef *qspi, uint32_t prescaler);
+void qspi_set_sample_shift(QSPI_HandleTypeDef *qspi, uint32_t shift);
+uint32_t qspi_clock_hz(QSPI_HandleTypeDef *qspi);
+void qspi_flash_power_on(void);
+void qspi_flash_power_off(void);
 
 #ifdef __cplusplus
 }
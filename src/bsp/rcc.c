@@ -1,6 +1,15 @@
 #include "rcc.h"
+#include "panic.h"
+#include "errors.h"
 #include "stm32h7xx_hal.h"
 
+#ifdef EMULATION
+void rcc_init(void)
+{
+    /* emulators don't model the supply and oscillator ready flags, stay on the reset hsi */
+    SystemCoreClockUpdate();
+}
+#else
 void rcc_init(void)
 {
     RCC_OscInitTypeDef osc_config = {0};
@@ -33,7 +42,7 @@ void rcc_init(void)
     osc_config.PLL.PLLFRACN = 0;
 
     if (HAL_RCC_OscConfig(&osc_config) != HAL_OK) {
-        while (1);
+        panic(ERR_PANIC);
     }
 
     clk_config.ClockType = RCC_CLOCKTYPE_HCLK | RCC_CLOCKTYPE_SYSCLK |
@@ -48,7 +57,8 @@ void rcc_init(void)
     clk_config.APB4CLKDivider = RCC_APB4_DIV2;
 
     if (HAL_RCC_ClockConfig(&clk_config, FLASH_LATENCY_4) != HAL_OK) {
-        while (1);
+        panic(ERR_PANIC);
     }
 }
+#endif
 
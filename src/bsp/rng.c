@@ -0,0 +1,123 @@
+#include "rng.h"
+
+/* words dropped after a seed error, as the reference manual asks for */
+#define RNG_SEED_DISCARD 12
+
+/* words compared by the startup health test */
+#define RNG_HEALTH_WORDS 16
+
+static uint32_t rng_last;
+static bool rng_last_valid;
+static bool rng_healthy;
+
+/*
+ * a seed error leaves the output unusable until SEIS is cleared and the
+ * words generated around it are thrown away. if SECS stays set the
+ * generator is restarted, see the rng section of the h7 errata
+ */
+static bool rng_recover(RNG_HandleTypeDef *rng)
+{
+    uint32_t word;
+
+    __HAL_RNG_CLEAR_IT(rng, RNG_IT_SEI);
+
+    for (uint32_t i = 0; i < RNG_SEED_DISCARD; i++) {
+        if (HAL_RNG_GenerateRandomNumber(rng, &word) != HAL_OK)
+            break;
+    }
+
+    if (!__HAL_RNG_GET_FLAG(rng, RNG_FLAG_SECS))
+        return true;
+
+    __HAL_RNG_DISABLE(rng);
+    __HAL_RNG_ENABLE(rng);
+    return !__HAL_RNG_GET_FLAG(rng, RNG_FLAG_SECS);
+}
+
+/**
+ * @brief   read one word
+ * @retval  false on a clock or seed error that couldn't be recovered, or if
+ *          the generator repeated its previous word (continuous test). a
+ *          failed health test disables the generator until the next init
+ */
+bool rng_read(RNG_HandleTypeDef *rng, uint32_t *value)
+{
+    uint32_t word;
+
+    if (!rng_healthy)
+        return false;
+
+    if (__HAL_RNG_GET_FLAG(rng, RNG_FLAG_SECS) && !rng_recover(rng))
+        return false;
+
+    if (HAL_RNG_GenerateRandomNumber(rng, &word) != HAL_OK) {
+        if (rng->ErrorCode != HAL_RNG_ERROR_SEED || !rng_recover(rng))
+            return false;
+        if (HAL_RNG_GenerateRandomNumber(rng, &word) != HAL_OK)
+            return false;
+    }
+
+    if (rng_last_valid && word == rng_last) {
+        rng_healthy = false;
+        return false;
+    }
+
+    rng_last = word;
+    rng_last_valid = true;
+    *value = word;
+    return true;
+}
+
+/**
+ * @brief   bring up the rng on hsi48 and run the startup health test
+ * @retval  false if there is no usable generator, e.g. under an emulator
+ */
+bool rng_init(RNG_HandleTypeDef *rng)
+{
+    uint32_t words[RNG_HEALTH_WORDS];
+    uint32_t start = HAL_GetTick();
+
+    __HAL_RCC_HSI48_ENABLE();
+    while (!__HAL_RCC_GET_FLAG(RCC_FLAG_HSI48RDY)) {
+        if (HAL_GetTick() - start > 10)
+            return false;
+    }
+
+    __HAL_RCC_RNG_CONFIG(RCC_RNGCLKSOURCE_HSI48);
+    __HAL_RCC_RNG_CLK_ENABLE();
+
+    rng->Instance = RNG;
+    rng->Init.ClockErrorDetection = RNG_CED_ENABLE;
+
+    if (HAL_RNG_Init(rng) != HAL_OK)
+        return false;
+
+    rng_last_valid = false;
+    rng_healthy = true;
+
+    /* stuck bits show up as repeated words, any two equal words fail */
+    for (uint32_t i = 0; i < RNG_HEALTH_WORDS; i++) {
+        if (!rng_read(rng, &words[i]))
+            return false;
+
+        for (uint32_t j = 0; j < i; j++) {
+            if (words[j] == words[i]) {
+                rng_healthy = false;
+                return false;
+            }
+        }
+    }
+
+    return true;
+}
+
+/* 0 to max - 1 for retry backoff, 0 without a working generator */
+uint32_t rng_jitter(RNG_HandleTypeDef *rng, uint32_t max)
+{
+    uint32_t word;
+
+    if (max == 0 || !rng_read(rng, &word))
+        return 0;
+
+    return word % max;
+}
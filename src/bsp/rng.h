@@ -0,0 +1,19 @@
+#ifndef RNG_H_
+#define RNG_H_
+
+#include "stm32h7xx_hal.h"
+#include <stdbool.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+bool rng_init(RNG_HandleTypeDef *rng);
+bool rng_read(RNG_HandleTypeDef *rng, uint32_t *value);
+uint32_t rng_jitter(RNG_HandleTypeDef *rng, uint32_t max);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
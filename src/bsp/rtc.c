@@ -0,0 +1,85 @@
+#include "rtc.h"
+#include "stm32h7xx_hal.h"
+
+/* readout protection level 0, anything else is a device that left the bench */
+#define RTC_RDP_LEVEL_0 0xAA
+
+static uint32_t rtc_bcd(uint32_t value)
+{
+    return (value >> 4) * 10 + (value & 0xF);
+}
+
+/* days since 1970-01-01, valid for the years 2000 to 2099 the rtc can hold */
+static uint32_t rtc_days(uint32_t year, uint32_t month, uint32_t day)
+{
+    static const uint16_t month_days[12] = {0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334};
+    uint32_t days = (year - 1970) * 365 + (year - 1969) / 4 + month_days[month - 1] + day - 1;
+
+    if (month > 2 && year % 4 == 0)
+        days++;
+
+    return days;
+}
+
+/* rtc registers readable, the shadow registers synced since the reset */
+static bool rtc_read(uint32_t *seconds)
+{
+    uint32_t start = HAL_GetTick();
+
+    /* two rtc clocks after a reset */
+    while (!(RTC->ISR & RTC_ISR_RSF)) {
+        if (HAL_GetTick() - start > 10)
+            return false;
+    }
+
+    if (!(RTC->ISR & RTC_ISR_INITS))
+        return false;
+
+    /* reading TR locks DR until it has been read as well */
+    uint32_t tr = RTC->TR;
+    uint32_t dr = RTC->DR;
+    uint32_t year = 2000 + rtc_bcd((dr & (RTC_DR_YT | RTC_DR_YU)) >> RTC_DR_YU_Pos);
+    uint32_t month = rtc_bcd((dr & (RTC_DR_MT | RTC_DR_MU)) >> RTC_DR_MU_Pos);
+    uint32_t day = rtc_bcd((dr & (RTC_DR_DT | RTC_DR_DU)) >> RTC_DR_DU_Pos);
+    uint32_t hours = rtc_bcd((tr & (RTC_TR_HT | RTC_TR_HU)) >> RTC_TR_HU_Pos);
+    uint32_t minutes = rtc_bcd((tr & (RTC_TR_MNT | RTC_TR_MNU)) >> RTC_TR_MNU_Pos);
+
+    if (month < 1 || month > 12 || day < 1)
+        return false;
+
+    /* 1 to 12 in the 12 hour format */
+    if (RTC->CR & RTC_CR_FMT)
+        hours = hours % 12 + ((tr & RTC_TR_PM) ? 12 : 0);
+
+    *seconds = ((rtc_days(year, month, day) * 24 + hours) * 60 + minutes) * 60 +
+               rtc_bcd((tr & (RTC_TR_ST | RTC_TR_SU)) >> RTC_TR_SU_Pos);
+    return true;
+}
+
+/**
+ * @brief   read the calendar the application keeps in the backup domain
+ * @param   seconds set to the unix time, the rtc is taken to run on utc
+ * @retval  false if the rtc isn't running or was never set, e.g. after the
+ *          backup domain lost power
+ */
+bool rtc_unix_time(uint32_t *seconds)
+{
+    if (!(RCC->BDCR & RCC_BDCR_RTCEN))
+        return false;
+
+    bool clock_was_on = __HAL_RCC_RTC_IS_CLK_ENABLED();
+    __HAL_RCC_RTC_CLK_ENABLE();
+
+    bool valid = rtc_read(seconds);
+
+    if (!clock_was_on)
+        __HAL_RCC_RTC_CLK_DISABLE();
+
+    return valid;
+}
+
+/* readout protection set, as it is on boards in the field */
+bool rtc_production_locked(void)
+{
+    return ((FLASH->OPTSR_CUR & FLASH_OPTSR_RDP_Msk) >> FLASH_OPTSR_RDP_Pos) != RTC_RDP_LEVEL_0;
+}
@@ -0,0 +1,18 @@
+#ifndef RTC_H_
+#define RTC_H_
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+bool rtc_unix_time(uint32_t *seconds);
+bool rtc_production_locked(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -1,4 +1,6 @@
 #include "usart.h"
+#include "panic.h"
+#include "errors.h"
 
 void usart_init(UART_HandleTypeDef *handle, USART_TypeDef *self)
 {
@@ -7,7 +9,7 @@ void usart_init(UART_HandleTypeDef *handle, USART_TypeDef *self)
     clock_usart1_config.Usart16ClockSelection = RCC_USART16CLKSOURCE_D2PCLK2;
 
     if (HAL_RCCEx_PeriphCLKConfig(&clock_usart1_config) != HAL_OK) {
-        while (1);
+        panic(ERR_PANIC);
     }
 
     __HAL_RCC_USART1_CLK_ENABLE();
@@ -25,19 +27,19 @@ void usart_init(UART_HandleTypeDef *handle, USART_TypeDef *self)
     handle->AdvancedInit.AdvFeatureInit = UART_ADVFEATURE_NO_INIT;
 
     if (HAL_UART_Init(handle) != HAL_OK) {
-        while (1);
+        panic(ERR_PANIC);
     }
 
     if (HAL_UARTEx_SetTxFifoThreshold(handle, UART_TXFIFO_THRESHOLD_1_8) != HAL_OK) {
-        while (1);
+        panic(ERR_PANIC);
     }
 
     if (HAL_UARTEx_SetRxFifoThreshold(handle, UART_RXFIFO_THRESHOLD_1_8) != HAL_OK) {
-        while (1);
+        panic(ERR_PANIC);
     }
 
     if (HAL_UARTEx_DisableFifoMode(handle) != HAL_OK) {
-        while (1);
+        panic(ERR_PANIC);
     }
 }
 
@@ -0,0 +1,200 @@
+#include "calibration.h"
+#include "adc.h"
+#include "qspi.h"
+#include "w25q.h"
+#include <string.h>
+
+extern QSPI_HandleTypeDef hqspi;
+extern Flash_T flash;
+extern ADC_HandleTypeDef adc;
+
+static const char * const qspi_patterns[QSPI_PATTERN_COUNT] = {"prbs7", "0x55/0xaa", "0x00/0xff"};
+
+static const uint8_t qspi_dummy_cycles[] = {2, 4, 6, 8};
+
+static uint8_t cal_reference[LAYOUT_SECTOR_SIZE];
+static uint8_t cal_buffer[LAYOUT_SECTOR_SIZE];
+
+void qspi_pattern_fill(uint8_t *buffer, uint32_t len, Qspi_Pattern_T pattern)
+{
+    uint8_t lfsr = 0x7F;
+
+    for (uint32_t i = 0; i < len; i++) {
+        uint8_t byte = 0;
+
+        switch (pattern) {
+        case QSPI_PATTERN_PRBS7:
+            //x^7 + x^6 + 1
+            for (uint32_t bit = 0; bit < 8; bit++) {
+                uint8_t next = ((lfsr >> 6) ^ (lfsr >> 5)) & 0x01;
+                lfsr = ((lfsr << 1) | next) & 0x7F;
+                byte = (byte << 1) | next;
+            }
+            break;
+        case QSPI_PATTERN_55AA:
+            byte = i & 1 ? 0xAA : 0x55;
+            break;
+        default:
+            byte = i & 1 ? 0xFF : 0x00;
+            break;
+        }
+
+        buffer[i] = byte;
+    }
+}
+
+const char *qspi_pattern_str(Qspi_Pattern_T pattern)
+{
+    if (pattern >= QSPI_PATTERN_COUNT)
+        return "unknown";
+
+    return qspi_patterns[pattern];
+}
+
+static bool cal_read_ok(void)
+{
+    for (uint32_t i = 0; i < QSPI_CAL_READS; i++) {
+        memset(cal_buffer, 0, sizeof(cal_buffer));
+
+        if (!flash.read_N_bytes(sizeof(cal_buffer), QSPI_CAL_SECTOR, cal_buffer))
+            return false;
+        if (memcmp(cal_buffer, cal_reference, sizeof(cal_buffer)) != 0)
+            return false;
+    }
+
+    return true;
+}
+
+static bool cal_setting_ok(uint32_t shift, uint8_t dummy_cycles)
+{
+    qspi_set_sample_shift(&hqspi, shift);
+
+    if (!flash.set_dummy_cycles(dummy_cycles))
+        return false;
+
+    return cal_read_ok();
+}
+
+static void cal_apply(Qspi_Calibration_T *cal)
+{
+#ifndef EMULATION
+    qspi_set_prescaler(&hqspi, cal->prescaler);
+    qspi_set_sample_shift(&hqspi, cal->sample_shift);
+#endif
+    flash.set_dummy_cycles(cal->dummy_cycles);
+}
+
+/*
+ * the reference sector only gets programmed when it doesn't read back at the
+ * safe clock, so a normal boot costs reads only
+ */
+static bool cal_reference_ok(void)
+{
+    qspi_pattern_fill(cal_reference, sizeof(cal_reference), QSPI_PATTERN_PRBS7);
+
+    if (cal_read_ok())
+        return true;
+
+    if (!flash.sector_erase(QSPI_CAL_SECTOR, QSPI_CAL_SECTOR))
+        return false;
+    if (!flash.write_N_bytes(sizeof(cal_reference), QSPI_CAL_SECTOR, cal_reference))
+        return false;
+
+    return cal_read_ok();
+}
+
+/**
+ * @brief   pick the fastest prescaler, sample shift and dummy cycles that read
+ *          the reference sector back reliably, and apply them
+ * @note    a setting only counts with margin: both sample shift settings have
+ *          to pass, and the dummy cycles get one step on top of the smallest
+ *          passing value. without a usable setting the safe clock is kept.
+ *          at the temperature extremes the search starts at the derated clock
+ */
+Qspi_Calibration_T qspi_calibrate(void)
+{
+    Qspi_Calibration_T cal = {false, QSPI_CAL_PRESCALER_SAFE, QSPI_SAMPLE_SHIFTING_NONE, 8, false, false, 0};
+    uint32_t prescaler_min = QSPI_CAL_PRESCALER_MIN;
+
+#ifdef EMULATION
+    //the emulator models neither the quadspi timing nor the temperature sensor
+    return cal;
+#endif
+
+    cal.temperature_valid = adc_read_temperature(&adc, &cal.temperature);
+
+    if (cal.temperature_valid &&
+        (cal.temperature < QSPI_DERATE_COLD_C || cal.temperature > QSPI_DERATE_HOT_C)) {
+        cal.derated = true;
+        prescaler_min = QSPI_DERATE_PRESCALER;
+    }
+
+    cal_apply(&cal);
+
+    if (!cal_reference_ok())
+        return cal;
+
+    for (uint32_t prescaler = prescaler_min; prescaler <= QSPI_CAL_PRESCALER_SAFE && !cal.ok; prescaler++) {
+        qspi_set_prescaler(&hqspi, prescaler);
+
+        for (uint32_t i = 0; i < sizeof(qspi_dummy_cycles); i++) {
+            uint8_t dummy_cycles = qspi_dummy_cycles[i];
+
+            if (!cal_setting_ok(QSPI_SAMPLE_SHIFTING_NONE, dummy_cycles) ||
+                !cal_setting_ok(QSPI_SAMPLE_SHIFTING_HALFCYCLE, dummy_cycles))
+                continue;
+
+            if (i + 1 < sizeof(qspi_dummy_cycles))
+                dummy_cycles = qspi_dummy_cycles[i + 1];
+
+            //the margin step has to hold up as well
+            if (!cal_setting_ok(QSPI_SAMPLE_SHIFTING_NONE, dummy_cycles) ||
+                !cal_setting_ok(QSPI_SAMPLE_SHIFTING_HALFCYCLE, dummy_cycles))
+                break;
+
+            cal.ok = true;
+            cal.prescaler = prescaler;
+            cal.sample_shift = QSPI_SAMPLE_SHIFTING_HALFCYCLE;
+            cal.dummy_cycles = dummy_cycles;
+            break;
+        }
+    }
+
+    if (!cal.ok) {
+        cal.prescaler = QSPI_CAL_PRESCALER_SAFE;
+        cal.sample_shift = QSPI_SAMPLE_SHIFTING_NONE;
+        cal.dummy_cycles = 8;
+    }
+
+    cal_apply(&cal);
+    return cal;
+}
+
+/* the safe setting without touching the reference sector, for a degraded bootloader */
+Qspi_Calibration_T qspi_calibration_safe(void)
+{
+    Qspi_Calibration_T cal = {false, QSPI_CAL_PRESCALER_SAFE, QSPI_SAMPLE_SHIFTING_NONE, 8, false, false, 0};
+
+    cal_apply(&cal);
+    return cal;
+}
+
+/* checked periodically while the console is up, recovery sessions can run for hours */
+bool qspi_temperature_shifted(const Qspi_Calibration_T *cal)
+{
+    int32_t temperature;
+
+#ifdef EMULATION
+    return false;
+#endif
+
+    if (!adc_read_temperature(&adc, &temperature))
+        return false;
+
+    //no reading at calibration time, so the clock isn't derated yet
+    if (!cal->temperature_valid)
+        return true;
+
+    return temperature - cal->temperature >= QSPI_RECAL_DELTA_C ||
+           cal->temperature - temperature >= QSPI_RECAL_DELTA_C;
+}
@@ -0,0 +1,54 @@
+#ifndef CALIBRATION_H_
+#define CALIBRATION_H_
+
+#include <stdint.h>
+#include "layout_map.h"
+
+/* last scratch sector, keeps the reference pattern across boots */
+#define QSPI_CAL_SECTOR         (LAYOUT_SCRATCH_BASE + LAYOUT_SCRATCH_LEN - LAYOUT_SECTOR_SIZE)
+
+/* 120 MHz, the w25q64 tops out at 133 MHz */
+#define QSPI_CAL_PRESCALER_MIN  1
+/* 60 MHz, known to work with any dummy cycle setting */
+#define QSPI_CAL_PRESCALER_SAFE 3
+
+/* passes each setting has to survive */
+#define QSPI_CAL_READS          4
+
+/*
+ * outside this range the clock is capped at QSPI_DERATE_PRESCALER (80 MHz).
+ * conservative figures for the industrial grade w25q64jv, check the ac
+ * characteristics of the part actually fitted
+ */
+#define QSPI_DERATE_COLD_C      (-20)
+#define QSPI_DERATE_HOT_C       85
+#define QSPI_DERATE_PRESCALER   2
+
+/* recalibrate once the die moved this far away from the last calibration */
+#define QSPI_RECAL_DELTA_C      20
+#define QSPI_TEMP_CHECK_MS      5000
+
+typedef enum {
+    QSPI_PATTERN_PRBS7 = 0,
+    QSPI_PATTERN_55AA,
+    QSPI_PATTERN_00FF,
+    QSPI_PATTERN_COUNT,
+} Qspi_Pattern_T;
+
+typedef struct {
+    bool ok;
+    uint32_t prescaler;
+    uint32_t sample_shift;
+    uint8_t dummy_cycles;
+    bool derated;
+    bool temperature_valid;
+    int32_t temperature;
+} Qspi_Calibration_T;
+
+void qspi_pattern_fill(uint8_t *buffer, uint32_t len, Qspi_Pattern_T pattern);
+const char *qspi_pattern_str(Qspi_Pattern_T pattern);
+Qspi_Calibration_T qspi_calibrate(void);
+Qspi_Calibration_T qspi_calibration_safe(void);
+bool qspi_temperature_shifted(const Qspi_Calibration_T *cal);
+
+#endif
@@ -0,0 +1,678 @@
+#include "commands.h"
+#include "boot.h"
+#include "calibration.h"
+#include "journal.h"
+#include "errors.h"
+#include "loader.h"
+#include "profile.h"
+#include "boot_log.h"
+#include "crc32.h"
+#include "eol.h"
+#include "manifest.h"
+#include "slots.h"
+#include "image_header.h"
+#include "boot_api.h"
+#include "le.h"
+#include "retry.h"
+#include "layout.h"
+#include "qspi.h"
+#include "w25q.h"
+#include <stdlib.h>
+#include <string.h>
+
+/* slowest setting tried by the signal integrity test, 30 MHz off a 240 MHz hclk */
+#define QSPI_TEST_PRESCALER_MAX 7
+
+extern QSPI_HandleTypeDef hqspi;
+extern Flash_T flash;
+
+static uint8_t qspi_test_buffer[LAYOUT_SECTOR_SIZE];
+static uint8_t qspi_read_buffer[LAYOUT_SECTOR_SIZE];
+
+#define HEXDUMP_MAX         4096
+#define MEMTEST_PASSES      100
+#define MEMTEST_PASSES_MAX  100000
+
+struct Dump_Region_T {
+    uint32_t base;
+    uint32_t len;
+};
+
+/* everything else may not be backed by memory and bus faults */
+static const Dump_Region_T dump_regions[] = {
+    {D1_ITCMRAM_BASE, 64 * 1024},
+    {FLASH_BANK1_BASE, 128 * 1024},
+    {D1_DTCMRAM_BASE, 128 * 1024},
+    {D1_AXISRAM_BASE, 512 * 1024},
+    {D2_AHBSRAM_BASE, 288 * 1024},
+    {D3_SRAM_BASE, 64 * 1024},
+    {QSPI_BASE, LAYOUT_MEMORY_SIZE},
+};
+
+static Hex_Parser_T load_parser;
+static Loader_T loader;
+static Hex_Record_T load_record;
+static uint32_t load_line;
+static Error_T load_error;
+
+static int command_reset(Console_T & console, int argc, char ** argv)
+{
+    console.print("resetting\r\n");
+    HAL_Delay(10);
+    NVIC_SystemReset();
+    return ERR_OK;
+}
+
+/**
+ * @brief   erase, program and read back one scratch sector per pattern
+ * @retval  offset of the first mismatch, or -1 if everything matched
+ */
+static int32_t qspi_test_run(Console_T & console, Qspi_Pattern_T pattern)
+{
+    if (!flash.sector_erase(Scratch_T::base, Scratch_T::base)) {
+        console.print("  %s: erase error\r\n", qspi_pattern_str(pattern));
+        return 0;
+    }
+
+    qspi_pattern_fill(qspi_test_buffer, sizeof(qspi_test_buffer), pattern);
+    memset(qspi_read_buffer, 0, sizeof(qspi_read_buffer));
+
+    if (!flash.write_N_bytes(sizeof(qspi_test_buffer), Scratch_T::base, qspi_test_buffer) ||
+        !flash.read_N_bytes(sizeof(qspi_read_buffer), Scratch_T::base, qspi_read_buffer)) {
+        console.print("  %s: transfer error\r\n", qspi_pattern_str(pattern));
+        return 0;
+    }
+
+    for (uint32_t i = 0; i < sizeof(qspi_test_buffer); i++) {
+        if (qspi_test_buffer[i] != qspi_read_buffer[i]) {
+            console.print("  %s: offset 0x%03lx wrote 0x%02x read 0x%02x\r\n", qspi_pattern_str(pattern),
+                          i, qspi_test_buffer[i], qspi_read_buffer[i]);
+            return i;
+        }
+    }
+
+    return -1;
+}
+
+/*
+ * step the quadspi clock up from QSPI_TEST_PRESCALER_MAX until a pattern
+ * doesn't survive an erase/program/read round trip through the scratch
+ * partition, to find the fastest clock a board can use.
+ */
+static int command_qspitest(Console_T & console, int argc, char ** argv)
+{
+    uint32_t saved = hqspi.Init.ClockPrescaler;
+    int32_t failed = -1;
+
+    for (int32_t prescaler = QSPI_TEST_PRESCALER_MAX; prescaler >= 0 && failed < 0; prescaler--) {
+        bool ok = true;
+
+        qspi_set_prescaler(&hqspi, prescaler);
+        console.print("prescaler %ld (%lu MHz)\r\n", prescaler, qspi_clock_hz(&hqspi) / 1000000);
+
+        for (uint32_t pattern = 0; pattern < QSPI_PATTERN_COUNT; pattern++) {
+            if (qspi_test_run(console, (Qspi_Pattern_T)pattern) >= 0)
+                ok = false;
+        }
+
+        if (!ok)
+            failed = prescaler;
+    }
+
+    qspi_set_prescaler(&hqspi, saved);
+
+    if (failed < 0) {
+        console.print("all prescalers passed\r\n");
+    } else if (failed == QSPI_TEST_PRESCALER_MAX) {
+        console.print("failed at the slowest prescaler %ld\r\n", failed);
+        return ERR_FLASH_MISMATCH;
+    } else {
+        console.print("first failing prescaler %ld, fastest reliable %ld\r\n", failed, failed + 1);
+    }
+
+    return ERR_OK;
+}
+
+/* newest first, also dumped at boot */
+void journal_print(Console_T & console)
+{
+    Journal_Entry_T entry;
+
+    for (uint32_t age = 0; journal_entry(age, &entry); age++) {
+        if (entry.op == JOURNAL_OP_BOOT) {
+            console.print("%5lu boot, reset flags 0x%08lx\r\n", entry.seq, entry.address);
+        } else {
+            console.print("%5lu %s 0x%06lx+0x%lx %s\r\n", entry.seq, journal_op_str(entry.op),
+                          entry.address, entry.len, journal_status_str(entry.status));
+        }
+    }
+}
+
+static int command_journal(Console_T & console, int argc, char ** argv)
+{
+    journal_print(console);
+    console.print("%lu flash retries since boot\r\n", retry_count());
+    return ERR_OK;
+}
+
+static Console_T * erase_console;
+
+static void chip_erase_progress(uint32_t percent, uint32_t elapsed_ms)
+{
+    erase_console->print("\r%3lu%% %lu.%lus", percent, elapsed_ms / 1000, elapsed_ms / 100 % 10);
+}
+
+/* wipes the metadata as well, so it asks for a confirmation on the command line */
+static int command_chiperase(Console_T & console, int argc, char ** argv)
+{
+    if (argc != 2 || strcmp(argv[1], "confirm") != 0) {
+        console.print("usage: chiperase confirm\r\n");
+        return ERR_BAD_ARGUMENT;
+    }
+
+    erase_console = &console;
+    slot_state_invalidate();
+    manifest_invalidate();
+
+    bool ok = flash.chip_erase(chip_erase_progress);
+    console.print("\r\n");
+
+    return ok ? ERR_OK : ERR_FLASH_ERASE;
+}
+
+/* boot phases in the order they ran, also printed at boot */
+void profile_print(Console_T & console)
+{
+    uint32_t last = 0;
+
+    for (uint32_t i = 0; i < profile_count(); i++) {
+        const Profile_Mark_T * mark = profile_get(i);
+
+        console.print("%-12s %8lu us  +%lu us\r\n", mark->name, mark->us, mark->us - last);
+        last = mark->us;
+    }
+}
+
+static int command_profile(Console_T & console, int argc, char ** argv)
+{
+    profile_print(console);
+    return ERR_OK;
+}
+
+#ifdef QSPI_TRACE
+static const char * const qspi_trace_lines[] = {"-", "1", "2", "4"};
+
+static int command_qspitrace(Console_T & console, int argc, char ** argv)
+{
+    uint32_t count = qspi_trace_count();
+    uint32_t cycles_per_us = SystemCoreClock / 1000000;
+
+    console.print("%lu transactions, newest first\r\n", count);
+    console.print("cmd  lines  dummy  address     len       time    status\r\n");
+
+    for (uint32_t age = 0; age < count && age < QSPI_TRACE_ENTRIES; age++) {
+        const Qspi_Trace_T * entry = qspi_trace_get(age);
+
+        console.print("%02x   %s-%s-%s  %5u  0x%08lx  %-8lu  %5lu us  %u\r\n",
+                      entry->instruction,
+                      qspi_trace_lines[(entry->lines >> 4) & 0x3],
+                      qspi_trace_lines[(entry->lines >> 2) & 0x3],
+                      qspi_trace_lines[entry->lines & 0x3],
+                      entry->dummy, entry->address, entry->len,
+                      entry->cycles / cycles_per_us, entry->status);
+    }
+
+    return ERR_OK;
+}
+#endif
+
+/* after an error the rest of the file is only consumed, up to its end record */
+static bool load_input(Console_T & console, const char * line)
+{
+    if (line == NULL) {
+        console.print("load aborted after %lu lines\r\n", load_line);
+        return true;
+    }
+
+    load_line++;
+
+    Error_T error = load_parser.parse(line, &load_record);
+    if (error == ERR_OK && load_error == ERR_OK)
+        error = loader.write(&load_record);
+
+    if (error != ERR_OK && load_error == ERR_OK) {
+        load_error = error;
+        console.print("line %lu: %s (0x%02x)\r\n", load_line, error_str(error), error);
+    }
+
+    if (load_record.type != HEX_RECORD_END)
+        return false;
+
+    if (load_error == ERR_OK)
+        load_error = loader.finish();
+
+    /* a revert of an earlier trial would swap the image just loaded back out */
+    if (load_error == ERR_OK)
+        load_error = slot_confirm();
+
+    boot_log(BOOT_LOG_UPLOAD, load_error, loader.bytes());
+
+    if (load_error == ERR_OK) {
+        console.print("loaded %lu bytes in %lu records, %lu ranges\r\n", loader.bytes(), loader.records(),
+                      loader.manifest()->count);
+    } else {
+        console.print("load failed, the primary slot is incomplete\r\n");
+    }
+
+    return true;
+}
+
+/*
+ * the file is pasted or sent as plain text right after the command, ihex and
+ * s-records are told apart per line. lines must fit CONSOLE_LINE_SIZE.
+ */
+static int command_load(Console_T & console, int argc, char ** argv)
+{
+    load_parser.reset();
+    loader.begin();
+    load_line = 0;
+    load_error = ERR_OK;
+
+    console.print("send the intel hex or s-record file, ctrl-c to abort\r\n");
+    console.capture(load_input);
+    return ERR_OK;
+}
+
+static Manifest_T verify_manifest;
+
+static int command_verify(Console_T & console, int argc, char ** argv)
+{
+    Error_T error = manifest_read(&verify_manifest);
+    if (error != ERR_OK)
+        return error;
+
+    for (uint32_t i = 0; i < verify_manifest.count; i++) {
+        console.print("  0x%06lx+0x%lx\r\n", verify_manifest.ranges[i].offset, verify_manifest.ranges[i].len);
+    }
+
+    error = manifest_verify(&verify_manifest);
+    if (error != ERR_OK)
+        return error;
+
+    console.print("image ok\r\n");
+    return ERR_OK;
+}
+
+static const char * info_tag(uint8_t tag)
+{
+    switch (tag) {
+    case IMAGE_TLV_BUILD_ID:
+        return "build id";
+    case IMAGE_TLV_GIT_HASH:
+        return "git hash";
+    case IMAGE_TLV_CHANNEL:
+        return "channel";
+    case IMAGE_TLV_CUSTOMER:
+        return "customer";
+    default:
+        return NULL;
+    }
+}
+
+//text as it is, anything else as hex, in pieces that fit a console line
+static void info_print_tlv(Console_T & console, const Image_Tlv_T * tlv)
+{
+    const char * name = info_tag(tlv->tag);
+    bool text = true;
+
+    if (name)
+        console.print("  %s:", name);
+    else
+        console.print("  0x%02x:", tlv->tag);
+
+    for (uint32_t i = 0; i < tlv->len; i++) {
+        if (tlv->value[i] < ' ' || tlv->value[i] >= 0x7F)
+            text = false;
+    }
+
+    for (uint32_t i = 0; i < tlv->len; i += 32) {
+        int piece = tlv->len - i < 32 ? tlv->len - i : 32;
+
+        if (text) {
+            console.print("%s%.*s", i == 0 ? " " : "", piece, (const char *)&tlv->value[i]);
+        } else {
+            for (int j = 0; j < piece; j++)
+                console.print(" %02x", tlv->value[i + j]);
+        }
+    }
+    console.print("\r\n");
+}
+
+/*
+ * mark the image in the secondary slot to be installed on the next reset. a
+ * dry run stops after the checks the install and the boot after it would
+ * run, so a host can try an image against a board without erasing anything
+ */
+static int command_install(Console_T & console, int argc, char ** argv)
+{
+    bool dryrun = argc == 2 && strcmp(argv[1], "dryrun") == 0;
+    Image_Header_T header;
+
+    if (argc > 2 || (argc == 2 && !dryrun)) {
+        console.print("usage: install [dryrun]\r\n");
+        return ERR_BAD_ARGUMENT;
+    }
+
+    Error_T error = slot_staged_check(Secondary_Slot_T::len, &header);
+    if (error != ERR_OK)
+        return error;
+
+    //linked to run from the primary slot, so the vectors are checked as they are
+    if (!flash.read_N_bytes(8, Secondary_Slot_T::base + header.entry_offset, qspi_read_buffer))
+        return ERR_FLASH_READ;
+
+    Boot_Check_T check = boot_check_vectors(le32_get(qspi_read_buffer), le32_get(qspi_read_buffer + 4),
+                                            header.length);
+    if (check != BOOT_CHECK_OK)
+        return check;
+
+    uint32_t size = (header.length + LAYOUT_SECTOR_SIZE - 1) / LAYOUT_SECTOR_SIZE * LAYOUT_SECTOR_SIZE;
+
+    console.print("version %lu, %lu bytes, %lu to swap\r\n", header.version, header.length, size);
+
+    if (slot_staged_installed(&header)) {
+        console.print("the image is installed already\r\n");
+        return ERR_OK;
+    }
+
+    if (dryrun) {
+        console.print("dry run ok, nothing written\r\n");
+        return ERR_OK;
+    }
+
+    error = slot_state_write(BOOT_API_SLOT_SECONDARY, size, SLOT_TRIAL_NONE);
+    if (error != ERR_OK)
+        return error;
+
+    console.print("installing on the next reset\r\n");
+    return ERR_OK;
+}
+
+static int command_info(Console_T & console, int argc, char ** argv)
+{
+    Image_Header_T header;
+
+    if (!flash.read_N_bytes(IMAGE_HEADER_SIZE + IMAGE_TLV_MAX, Primary_Slot_T::base, qspi_read_buffer))
+        return ERR_FLASH_READ;
+
+    image_header_decode(&header, qspi_read_buffer);
+
+    Error_T error = image_header_check(&header);
+    if (error != ERR_OK)
+        return error;
+
+    console.print("version %lu, %lu bytes, vector table at 0x%lx\r\n", header.version, header.length,
+                  header.entry_offset);
+    console.print("board %lu rev %lu, flash %lu bytes, expires %lu\r\n", header.board, header.board_rev,
+                  header.flash_size, header.expiry);
+
+    const uint8_t * area = qspi_read_buffer + IMAGE_HEADER_SIZE;
+    uint32_t offset = 0;
+    Image_Tlv_T tlv;
+
+    error = image_tlv_check(&header, area);
+    if (error != ERR_OK)
+        return error;
+
+    while (image_tlv_next(area, header.tlv_len, &offset, &tlv))
+        info_print_tlv(console, &tlv);
+
+    return ERR_OK;
+}
+
+static bool parse_u32(const char * text, uint32_t * value)
+{
+    char * end;
+
+    *value = strtoul(text, &end, 0);
+    return *text != '\0' && *end == '\0';
+}
+
+static bool dump_allowed(uint32_t address, uint32_t N)
+{
+    for (const Dump_Region_T & region : dump_regions) {
+        if (address >= region.base && address - region.base < region.len && N <= region.len - (address - region.base))
+            return true;
+    }
+
+    return false;
+}
+
+static bool in_xip_window(uint32_t address)
+{
+    return address >= QSPI_BASE && address - QSPI_BASE < LAYOUT_MEMORY_SIZE;
+}
+
+/*
+ * the window is only mapped for the duration of the command, the flash
+ * commands of the console need the peripheral in indirect mode
+ */
+static int command_hexdump(Console_T & console, int argc, char ** argv)
+{
+    uint32_t address, N = 256;
+
+    if (argc < 2 || !parse_u32(argv[1], &address) || (argc > 2 && !parse_u32(argv[2], &N)))
+        return ERR_BAD_ARGUMENT;
+    if (N == 0 || N > HEXDUMP_MAX)
+        return ERR_BAD_ARGUMENT;
+    if (!dump_allowed(address, N))
+        return ERR_OUT_OF_RANGE;
+
+    bool xip = in_xip_window(address);
+    if (xip) {
+        flash.memory_map();
+        SCB_InvalidateDCache_by_Addr((void *)address, N);
+    }
+
+    const volatile uint8_t * data = (const volatile uint8_t *)address;
+    char ascii[17];
+
+    for (uint32_t i = 0; i < N; i += 16) {
+        uint32_t row = N - i < 16 ? N - i : 16;
+
+        console.print("%08lx:", address + i);
+        for (uint32_t j = 0; j < 16; j++) {
+            if (j < row) {
+                uint8_t byte = data[i + j];
+                console.print(" %02x", byte);
+                ascii[j] = byte >= ' ' && byte < 0x7F ? byte : '.';
+            } else {
+                console.print("   ");
+                ascii[j] = ' ';
+            }
+        }
+        ascii[16] = '\0';
+        console.print("  |%s|\r\n", ascii);
+    }
+
+    if (xip)
+        flash.memory_unmap();
+
+    return ERR_OK;
+}
+
+/*
+ * read a range through the xip window over and over and compare it against
+ * an indirect mode read, which doesn't go through the cache or the prefetch
+ */
+static int command_memtest(Console_T & console, int argc, char ** argv)
+{
+    uint32_t address, N, passes = MEMTEST_PASSES;
+    uint32_t failed = 0;
+
+    if (argc < 3 || !parse_u32(argv[1], &address) || !parse_u32(argv[2], &N) ||
+        (argc > 3 && !parse_u32(argv[3], &passes)))
+        return ERR_BAD_ARGUMENT;
+    if (N == 0 || N > sizeof(qspi_test_buffer) || passes == 0 || passes > MEMTEST_PASSES_MAX)
+        return ERR_BAD_ARGUMENT;
+    if (!in_xip_window(address) || !dump_allowed(address, N))
+        return ERR_OUT_OF_RANGE;
+
+    if (!flash.read_N_bytes(N, address - QSPI_BASE, qspi_test_buffer))
+        return ERR_FLASH_READ;
+
+    flash.memory_map();
+
+    for (uint32_t pass = 0; pass < passes; pass++) {
+        SCB_InvalidateDCache_by_Addr((void *)address, N);
+        memcpy(qspi_read_buffer, (const void *)address, N);
+
+        for (uint32_t i = 0; i < N; i++) {
+            if (qspi_read_buffer[i] != qspi_test_buffer[i]) {
+                //one line per failing pass is enough to see a pattern
+                console.print("pass %lu: 0x%08lx read 0x%02x expected 0x%02x\r\n", pass, address + i,
+                              qspi_read_buffer[i], qspi_test_buffer[i]);
+                failed++;
+                break;
+            }
+        }
+    }
+
+    flash.memory_unmap();
+
+    console.print("%lu of %lu passes failed\r\n", failed, passes);
+    return failed ? ERR_FLASH_MISMATCH : ERR_OK;
+}
+
+/* xip window addresses like the other flash commands, whole sectors only */
+static int command_erase(Console_T & console, int argc, char ** argv)
+{
+    uint32_t address, N;
+
+    if (argc != 3 || !parse_u32(argv[1], &address) || !parse_u32(argv[2], &N) || N == 0)
+        return ERR_BAD_ARGUMENT;
+    if (!in_xip_window(address) || N > LAYOUT_MEMORY_SIZE - (address - QSPI_BASE))
+        return ERR_OUT_OF_RANGE;
+    if ((address - QSPI_BASE) % LAYOUT_SECTOR_SIZE || N % LAYOUT_SECTOR_SIZE)
+        return ERR_UNALIGNED;
+
+    uint32_t start = address - QSPI_BASE;
+
+    if (Metadata_T::overlaps(start, N)) {
+        slot_state_invalidate();
+        manifest_invalidate();
+    }
+
+    if (!flash.sector_erase(start, start + N - 1))
+        return ERR_FLASH_ERASE;
+
+    console.print("erased 0x%08lx+0x%lx\r\n", address, N);
+    return ERR_OK;
+}
+
+/* the crc lets a production script compare a range without reading it out */
+static int command_blankcheck(Console_T & console, int argc, char ** argv)
+{
+    uint32_t address, N;
+    uint32_t dirty = 0, first_dirty = 0;
+    Crc32_T crc;
+
+    if (argc != 3 || !parse_u32(argv[1], &address) || !parse_u32(argv[2], &N) || N == 0)
+        return ERR_BAD_ARGUMENT;
+    if (!in_xip_window(address) || N > LAYOUT_MEMORY_SIZE - (address - QSPI_BASE))
+        return ERR_OUT_OF_RANGE;
+
+    for (uint32_t offset = 0; offset < N; offset += sizeof(qspi_read_buffer)) {
+        uint32_t chunk = N - offset < sizeof(qspi_read_buffer) ? N - offset : sizeof(qspi_read_buffer);
+
+        if (!flash.read_N_bytes(chunk, address - QSPI_BASE + offset, qspi_read_buffer))
+            return ERR_FLASH_READ;
+
+        crc.update(qspi_read_buffer, chunk);
+
+        for (uint32_t i = 0; i < chunk; i++) {
+            if (qspi_read_buffer[i] != 0xFF) {
+                if (dirty == 0)
+                    first_dirty = address + offset + i;
+                dirty++;
+            }
+        }
+    }
+
+    console.print("crc32 0x%08lx\r\n", crc.finalize());
+    if (dirty == 0) {
+        console.print("blank\r\n");
+        return ERR_OK;
+    }
+
+    console.print("%lu bytes programmed, the first at 0x%08lx\r\n", dirty, first_dirty);
+    return ERR_FLASH_NOT_BLANK;
+}
+
+#ifdef FLASH_FAULTS
+static int command_faults(Console_T & console, int argc, char ** argv)
+{
+    uint32_t write_one_in, flip_one_in, busy_max_us;
+
+    if (argc == 2 && strcmp(argv[1], "off") == 0) {
+        flash.set_faults(0, 0, 0);
+    } else if (argc == 4) {
+        if (!parse_u32(argv[1], &write_one_in) || !parse_u32(argv[2], &flip_one_in) ||
+            !parse_u32(argv[3], &busy_max_us))
+            return ERR_BAD_ARGUMENT;
+        flash.set_faults(write_one_in, flip_one_in, busy_max_us);
+    } else if (argc != 1) {
+        return ERR_BAD_ARGUMENT;
+    }
+
+    const Flash_Faults_T * faults = flash.faults();
+
+    console.print("fail 1 in %lu writes, flip a bit in 1 in %lu reads, busy up to %lu us longer\r\n",
+                  faults->write_one_in, faults->flip_one_in, faults->busy_max_us);
+    console.print("%lu writes failed, %lu bits flipped, %lu waits delayed\r\n",
+                  faults->writes_failed, faults->bits_flipped, faults->busy_delayed);
+    return ERR_OK;
+}
+#endif
+
+//returns only if the application can't be started
+static int command_boot(Console_T & console, int argc, char ** argv)
+{
+    console.print("starting the application\r\n");
+    return boot_application();
+}
+
+#ifdef EOL_TEST
+static int command_eoltest(Console_T & console, int argc, char ** argv)
+{
+    return eol_run(console);
+}
+#endif
+
+const Console_Command_T commands[] = {
+    {"reset", "reset the board", command_reset},
+    {"boot", "start the application in the primary slot", command_boot},
+    {"load", "program an intel hex or s-record file into the primary slot", command_load},
+    {"verify", "check the loaded ranges against the manifest hash", command_verify},
+    {"install", "install [dryrun], install the image in the secondary slot on the next reset", command_install},
+    {"info", "show the image header and metadata of the primary slot", command_info},
+    {"hexdump", "hexdump <addr> [len], ram, internal flash or the xip window", command_hexdump},
+    {"memtest", "memtest <addr> <len> [passes], compare xip reads against indirect reads", command_memtest},
+    {"profile", "show how long each boot phase took", command_profile},
+    {"erase", "erase <addr> <len>, whole sectors of the xip window", command_erase},
+    {"blankcheck", "blankcheck <addr> <len>, check a range reads erased and print its crc", command_blankcheck},
+    {"chiperase", "chiperase confirm, erase the whole external flash", command_chiperase},
+    {"journal", "list the last flash operations, kept across soft resets", command_journal},
+    {"qspitest", "find the fastest reliable qspi clock using the scratch partition", command_qspitest},
+#ifdef EOL_TEST
+    {"eoltest", "run the production end of line test", command_eoltest},
+#endif
+#ifdef FLASH_FAULTS
+    {"faults", "faults [off | <write 1 in> <flip 1 in> <busy us>], inject flash faults, 0 for never", command_faults},
+#endif
+#ifdef QSPI_TRACE
+    {"qspitrace", "list the last qspi transactions", command_qspitrace},
+#endif
+};
+
+const uint32_t commands_count = sizeof(commands) / sizeof(commands[0]);
@@ -0,0 +1,12 @@
+#ifndef COMMANDS_H_
+#define COMMANDS_H_
+
+#include "console.h"
+
+extern const Console_Command_T commands[];
+extern const uint32_t commands_count;
+
+void journal_print(Console_T & console);
+void profile_print(Console_T & console);
+
+#endif
@@ -0,0 +1,596 @@
+#include "console.h"
+#include "errors.h"
+#include <stdarg.h>
+#include <stdio.h>
+#include <string.h>
+
+#define CONSOLE_PROMPT "> "
+
+/* handled by the console itself, listed after the command table */
+static const Console_Command_T console_builtins[] = {
+    {"help", "list the commands", NULL},
+    {"script", "run the following lines up to 'end', stop at the first error", NULL},
+};
+
+#define CONSOLE_BUILTINS_COUNT (sizeof(console_builtins) / sizeof(console_builtins[0]))
+
+enum {
+    CONSOLE_ESCAPE_NONE = 0,
+    CONSOLE_ESCAPE_START,   //got ESC
+    CONSOLE_ESCAPE_CSI,     //got ESC [
+    CONSOLE_ESCAPE_PARAM,   //got ESC [ <digit>, waiting for ~
+};
+
+Console_T::Console_T(void)
+{
+    m_serial = NULL;
+    m_commands = NULL;
+    m_commands_count = 0;
+    m_rx_head = 0;
+    m_rx_tail = 0;
+    m_len = 0;
+    m_cursor = 0;
+    m_escape = CONSOLE_ESCAPE_NONE;
+    m_last_cr = false;
+    m_history_count = 0;
+    m_history_next = 0;
+    m_history_pos = 0;
+    m_script = false;
+    m_script_failed = false;
+    m_script_line = 0;
+    m_capture = NULL;
+    m_session = false;
+    m_last_input = 0;
+}
+
+/**
+ * @brief   attach the console to an initialized uart and start receiving
+ * @param   serial      uart handle, its irq has to call irq_handler()
+ * @param   commands    command table
+ * @param   count       number of entries in the command table
+ */
+void Console_T::init(UART_HandleTypeDef * serial, const Console_Command_T * commands, uint32_t count)
+{
+    m_serial = serial;
+    m_commands = commands;
+    m_commands_count = count;
+
+    __HAL_UART_ENABLE_IT(m_serial, UART_IT_RXNE);
+
+    m_prompt();
+}
+
+/**
+ * @brief   move received bytes into the rx ring, called from the uart irq
+ * @note    bytes arriving while the ring is full are dropped
+ */
+void Console_T::irq_handler(void)
+{
+    uint32_t isr = m_serial->Instance->ISR;
+
+    if (isr & (USART_ISR_ORE | USART_ISR_FE | USART_ISR_NE)) {
+        m_serial->Instance->ICR = USART_ICR_ORECF | USART_ICR_FECF | USART_ICR_NECF;
+    }
+
+    if (isr & USART_ISR_RXNE_RXFNE) {
+        uint8_t c = (uint8_t)m_serial->Instance->RDR;
+        uint32_t next = (m_rx_head + 1) % CONSOLE_RX_SIZE;
+
+        if (next != m_rx_tail) {
+            m_rx[m_rx_head] = c;
+            m_rx_head = next;
+        }
+    }
+}
+
+bool Console_T::read_char(uint8_t * c)
+{
+    if (m_rx_tail == m_rx_head)
+        return false;
+
+    *c = m_rx[m_rx_tail];
+    m_rx_tail = (m_rx_tail + 1) % CONSOLE_RX_SIZE;
+    return true;
+}
+
+/**
+ * @brief   process everything received so far, never blocks on input
+ */
+void Console_T::poll(void)
+{
+    uint8_t c;
+    bool input = false;
+
+    while (read_char(&c)) {
+        m_input(c);
+        input = true;
+    }
+
+    //taken after the commands ran, a long chip erase doesn't count as idle
+    if (input) {
+        m_session = true;
+        m_last_input = HAL_GetTick();
+    }
+}
+
+/* true from the first input until the session is closed */
+bool Console_T::session(void)
+{
+    return m_session;
+}
+
+/**
+ * @brief   check for a session that went quiet
+ * @retval  true once input was received and nothing followed for CONSOLE_IDLE_MS
+ */
+bool Console_T::idle(void)
+{
+    return CONSOLE_IDLE_MS && m_session && HAL_GetTick() - m_last_input >= CONSOLE_IDLE_MS;
+}
+
+/**
+ * @brief   drop the session state as if nothing had been received
+ * @note    an upload in progress gets aborted like with ctrl-c, a script and
+ *          the partial line are discarded
+ */
+void Console_T::close_session(void)
+{
+    if (m_capture) {
+        m_capture(*this, NULL);
+        m_capture = NULL;
+    }
+
+    m_script = false;
+    m_len = 0;
+    m_cursor = 0;
+    m_escape = CONSOLE_ESCAPE_NONE;
+    m_last_cr = false;
+    m_session = false;
+
+    m_prompt();
+}
+
+void Console_T::write(const char * data, uint32_t N)
+{
+    HAL_UART_Transmit(m_serial, (uint8_t *)data, N, 1000);
+}
+
+void Console_T::print(const char * fmt, ...)
+{
+    char buffer[128];
+    va_list args;
+
+    va_start(args, fmt);
+    int len = vsnprintf(buffer, sizeof(buffer), fmt, args);
+    va_end(args);
+
+    if (len < 0)
+        return;
+    if ((uint32_t)len >= sizeof(buffer))
+        len = sizeof(buffer) - 1;
+
+    write(buffer, len);
+}
+
+void Console_T::m_prompt(void)
+{
+    write(CONSOLE_PROMPT, strlen(CONSOLE_PROMPT));
+}
+
+/**
+ * @brief   reprint the line from the cursor to its end and put the cursor back
+ * @param   erase   number of stale characters past the new end of the line
+ */
+void Console_T::m_redraw_tail(uint32_t erase)
+{
+    uint32_t back = m_len - m_cursor + erase;
+
+    write(&m_line[m_cursor], m_len - m_cursor);
+    for (uint32_t i = 0; i < erase; i++)
+        write(" ", 1);
+    if (back)
+        print("\x1b[%luD", back);
+}
+
+/**
+ * @brief   replace the whole line being edited, used by history browsing
+ */
+void Console_T::m_set_line(const char * line)
+{
+    uint32_t old_len = m_len;
+
+    if (m_cursor)
+        print("\x1b[%luD", m_cursor);
+
+    m_len = strlen(line);
+    memcpy(m_line, line, m_len);
+    m_cursor = 0;
+
+    m_redraw_tail(old_len > m_len ? old_len - m_len : 0);
+
+    if (m_len)
+        print("\x1b[%luC", m_len);
+    m_cursor = m_len;
+}
+
+void Console_T::m_insert(char c)
+{
+    if (m_len >= CONSOLE_LINE_SIZE - 1)
+        return;
+
+    memmove(&m_line[m_cursor + 1], &m_line[m_cursor], m_len - m_cursor);
+    m_line[m_cursor] = c;
+    m_len++;
+
+    write(&c, 1);
+    m_cursor++;
+    m_redraw_tail(0);
+}
+
+void Console_T::m_backspace(void)
+{
+    if (m_cursor == 0)
+        return;
+
+    m_left();
+    m_delete();
+}
+
+void Console_T::m_delete(void)
+{
+    if (m_cursor == m_len)
+        return;
+
+    memmove(&m_line[m_cursor], &m_line[m_cursor + 1], m_len - m_cursor - 1);
+    m_len--;
+    m_redraw_tail(1);
+}
+
+void Console_T::m_left(void)
+{
+    if (m_cursor == 0)
+        return;
+
+    write("\b", 1);
+    m_cursor--;
+}
+
+void Console_T::m_right(void)
+{
+    if (m_cursor == m_len)
+        return;
+
+    write(&m_line[m_cursor], 1);
+    m_cursor++;
+}
+
+void Console_T::m_history_push(void)
+{
+    uint32_t last = (m_history_next + CONSOLE_HISTORY_SIZE - 1) % CONSOLE_HISTORY_SIZE;
+
+    if (m_len == 0)
+        return;
+    if (m_history_count && strcmp(m_history[last], m_line) == 0)
+        return;
+
+    strcpy(m_history[m_history_next], m_line);
+    m_history_next = (m_history_next + 1) % CONSOLE_HISTORY_SIZE;
+    if (m_history_count < CONSOLE_HISTORY_SIZE)
+        m_history_count++;
+}
+
+/**
+ * @brief   step through the history, position 0 is the (empty) new line
+ */
+void Console_T::m_history_browse(bool older)
+{
+    if (older) {
+        if (m_history_pos == m_history_count)
+            return;
+        m_history_pos++;
+    } else {
+        if (m_history_pos == 0)
+            return;
+        m_history_pos--;
+    }
+
+    if (m_history_pos == 0) {
+        m_set_line("");
+        return;
+    }
+
+    uint32_t index = (m_history_next + CONSOLE_HISTORY_SIZE - m_history_pos) % CONSOLE_HISTORY_SIZE;
+    m_set_line(m_history[index]);
+}
+
+const char * Console_T::m_name(uint32_t index)
+{
+    if (index < m_commands_count)
+        return m_commands[index].name;
+
+    return console_builtins[index - m_commands_count].name;
+}
+
+/**
+ * @brief   complete the command name under the cursor
+ * @note    a unique match is completed with a trailing space, several matches
+ *          are completed up to their common prefix and listed
+ */
+void Console_T::m_complete(void)
+{
+    const char * first = NULL;
+    uint32_t start = m_cursor;
+    uint32_t common = 0;
+    uint32_t matches = 0;
+
+    for (uint32_t i = 0; i < m_cursor; i++) {
+        if (m_line[i] == ' ')
+            return;
+    }
+
+    for (uint32_t i = 0; i < m_commands_count + CONSOLE_BUILTINS_COUNT; i++) {
+        const char * name = m_name(i);
+
+        if (strncmp(name, m_line, m_cursor) != 0)
+            continue;
+
+        if (matches == 0) {
+            first = name;
+            common = strlen(name);
+        } else {
+            uint32_t n = m_cursor;
+            while (n < common && name[n] == first[n])
+                n++;
+            common = n;
+        }
+        matches++;
+    }
+
+    if (matches == 0)
+        return;
+
+    for (uint32_t i = start; i < common; i++)
+        m_insert(first[i]);
+
+    if (matches == 1) {
+        m_insert(' ');
+        return;
+    }
+
+    if (common > start)
+        return;
+
+    write("\r\n", 2);
+    for (uint32_t i = 0; i < m_commands_count + CONSOLE_BUILTINS_COUNT; i++) {
+        const char * name = m_name(i);
+
+        if (strncmp(name, m_line, m_cursor) == 0)
+            print("%s  ", name);
+    }
+    write("\r\n", 2);
+    m_prompt();
+    write(m_line, m_len);
+    if (m_len > m_cursor)
+        print("\x1b[%luD", m_len - m_cursor);
+}
+
+void Console_T::m_help(void)
+{
+    for (uint32_t i = 0; i < m_commands_count; i++) {
+        print("%-10s %s\r\n", m_commands[i].name, m_commands[i].help);
+    }
+    for (uint32_t i = 0; i < CONSOLE_BUILTINS_COUNT; i++) {
+        print("%-10s %s\r\n", console_builtins[i].name, console_builtins[i].help);
+    }
+}
+
+/**
+ * @brief   split a line into arguments and run the command
+ * @retval  ERR_OK on success, the command's error code otherwise
+ */
+int Console_T::m_run(char * line)
+{
+    char * argv[CONSOLE_MAX_ARGS];
+    int argc = 0;
+
+    char * token = strtok(line, " ");
+    while (token && argc < CONSOLE_MAX_ARGS) {
+        argv[argc++] = token;
+        token = strtok(NULL, " ");
+    }
+
+    if (argc == 0)
+        return 0;
+
+    if (strcmp(argv[0], "help") == 0) {
+        m_help();
+        return 0;
+    }
+
+    if (strcmp(argv[0], "script") == 0) {
+        m_script = true;
+        m_script_failed = false;
+        m_script_line = 0;
+        return 0;
+    }
+
+    for (uint32_t i = 0; i < m_commands_count; i++) {
+        if (strcmp(m_commands[i].name, argv[0]) == 0) {
+            int ret = m_commands[i].handler(*this, argc, argv);
+            if (ret != ERR_OK)
+                print("%s failed: %s (0x%02x)\r\n", argv[0], error_str(ret), ret);
+            return ret;
+        }
+    }
+
+    print("unknown command '%s', try help\r\n", argv[0]);
+    return ERR_UNKNOWN_COMMAND;
+}
+
+void Console_T::m_execute(void)
+{
+    write("\r\n", 2);
+
+    m_line[m_len] = '\0';
+    m_history_push();
+    m_history_pos = 0;
+
+    m_run(m_line);
+
+    m_len = 0;
+    m_cursor = 0;
+    if (!m_script && !m_capture)
+        m_prompt();
+}
+
+/**
+ * @brief   script mode, lines are run as they arrive without editing or echo
+ * @note    empty lines and lines starting with # are skipped. after a failing
+ *          line the rest of the script is only consumed, up to its 'end' line
+ */
+void Console_T::m_script_input(uint8_t c)
+{
+    if (c != '\r' && c != '\n') {
+        if (m_len < CONSOLE_LINE_SIZE - 1)
+            m_line[m_len++] = c;
+        return;
+    }
+
+    m_line[m_len] = '\0';
+    m_len = 0;
+    m_script_line++;
+
+    char * line = m_line;
+    while (*line == ' ')
+        line++;
+
+    if (strcmp(line, "end") == 0) {
+        m_script = false;
+        if (m_script_failed)
+            print("script aborted\r\n");
+        else
+            print("script ok, %lu lines\r\n", m_script_line - 1);
+        m_prompt();
+        return;
+    }
+
+    if (m_script_failed || *line == '\0' || *line == '#')
+        return;
+
+    print("%s\r\n", line);
+    if (m_run(line) != 0) {
+        m_script_failed = true;
+        print("script error at line %lu\r\n", m_script_line);
+    }
+}
+
+/**
+ * @brief   hand the following lines to a command, e.g. an upload
+ * @note    meant to be called from a command handler, the prompt comes back
+ *          once the handler is done with the input
+ */
+void Console_T::capture(Console_Capture_T handler)
+{
+    m_capture = handler;
+}
+
+void Console_T::m_capture_input(uint8_t c)
+{
+    if (c == 0x03) {
+        m_capture(*this, NULL);
+        m_capture = NULL;
+        m_len = 0;
+        write("^C\r\n", 4);
+        if (!m_script)
+            m_prompt();
+        return;
+    }
+
+    if (c != '\r' && c != '\n') {
+        if (m_len < CONSOLE_LINE_SIZE - 1)
+            m_line[m_len++] = c;
+        return;
+    }
+
+    m_line[m_len] = '\0';
+    m_len = 0;
+
+    if (m_capture(*this, m_line)) {
+        m_capture = NULL;
+        if (!m_script)
+            m_prompt();
+    }
+}
+
+void Console_T::m_input(uint8_t c)
+{
+    //treat \r\n as a single line ending
+    if (c == '\n' && m_last_cr) {
+        m_last_cr = false;
+        return;
+    }
+    m_last_cr = c == '\r';
+
+    //a command run from a script may capture the next lines too
+    if (m_capture) {
+        m_capture_input(c);
+        return;
+    }
+
+    if (m_script) {
+        m_script_input(c);
+        return;
+    }
+
+    switch (m_escape) {
+    case CONSOLE_ESCAPE_START:
+        m_escape = c == '[' ? CONSOLE_ESCAPE_CSI : CONSOLE_ESCAPE_NONE;
+        return;
+    case CONSOLE_ESCAPE_CSI:
+        m_escape = CONSOLE_ESCAPE_NONE;
+        switch (c) {
+        case 'A': m_history_browse(true); break;
+        case 'B': m_history_browse(false); break;
+        case 'C': m_right(); break;
+        case 'D': m_left(); break;
+        case '3': m_escape = CONSOLE_ESCAPE_PARAM; break;
+        default: break;
+        }
+        return;
+    case CONSOLE_ESCAPE_PARAM:
+        m_escape = CONSOLE_ESCAPE_NONE;
+        if (c == '~')
+            m_delete();
+        return;
+    default:
+        break;
+    }
+
+    switch (c) {
+    case '\r':
+    case '\n':
+        m_execute();
+        break;
+    case 0x1B:
+        m_escape = CONSOLE_ESCAPE_START;
+        break;
+    case '\b':
+    case 0x7F:
+        m_backspace();
+        break;
+    case '\t':
+        m_complete();
+        break;
+    case 0x03: //ctrl-c drops the line
+        write("^C\r\n", 4);
+        m_len = 0;
+        m_cursor = 0;
+        m_history_pos = 0;
+        m_prompt();
+        break;
+    default:
+        if (c >= ' ' && c < 0x7F)
+            m_insert(c);
+        break;
+    }
+}
@@ -0,0 +1,98 @@
+#ifndef CONSOLE_H_
+#define CONSOLE_H_
+
+#include "stm32h7xx_hal.h"
+
+#define CONSOLE_LINE_SIZE       160
+#define CONSOLE_HISTORY_SIZE    8
+/* holds ~350ms at 115200, a sector erase during an upload must not overflow it */
+#define CONSOLE_RX_SIZE         4096
+#define CONSOLE_MAX_ARGS        8
+
+/*
+ * a session that sends nothing for this long is closed, the uart may be
+ * shared with equipment that opens it and then goes quiet. 0 never closes
+ */
+#ifndef CONSOLE_IDLE_MS
+#define CONSOLE_IDLE_MS         60000
+#endif
+
+class Console_T;
+
+/* handlers return ERR_OK or one of the codes from errors.h */
+typedef struct {
+    const char * name;
+    const char * help;
+    int (*handler)(Console_T & console, int argc, char ** argv);
+} Console_Command_T;
+
+/*
+ * takes the raw lines after a command, without echo or editing. called with
+ * NULL when the user aborts with ctrl-c. returns true once it has seen enough
+ */
+typedef bool (*Console_Capture_T)(Console_T & console, const char * line);
+
+class Console_T
+{
+private:
+    UART_HandleTypeDef * m_serial;
+    const Console_Command_T * m_commands;
+    uint32_t m_commands_count;
+
+    uint8_t m_rx[CONSOLE_RX_SIZE];
+    volatile uint32_t m_rx_head;
+    volatile uint32_t m_rx_tail;
+
+    char m_line[CONSOLE_LINE_SIZE];
+    uint32_t m_len;
+    uint32_t m_cursor;
+    uint8_t m_escape;
+    bool m_last_cr;
+
+    char m_history[CONSOLE_HISTORY_SIZE][CONSOLE_LINE_SIZE];
+    uint32_t m_history_count;
+    uint32_t m_history_next;
+    uint32_t m_history_pos;
+
+    bool m_script;
+    bool m_script_failed;
+    uint32_t m_script_line;
+
+    Console_Capture_T m_capture;
+
+    bool m_session;
+    uint32_t m_last_input;
+
+    void m_prompt(void);
+    void m_redraw_tail(uint32_t erase);
+    void m_set_line(const char * line);
+    void m_insert(char c);
+    void m_backspace(void);
+    void m_delete(void);
+    void m_left(void);
+    void m_right(void);
+    void m_history_push(void);
+    void m_history_browse(bool older);
+    const char * m_name(uint32_t index);
+    void m_complete(void);
+    int m_run(char * line);
+    void m_execute(void);
+    void m_help(void);
+    void m_script_input(uint8_t c);
+    void m_capture_input(uint8_t c);
+    void m_input(uint8_t c);
+public:
+    Console_T(void);
+    void init(UART_HandleTypeDef * serial, const Console_Command_T * commands, uint32_t count);
+    void irq_handler(void);
+    bool read_char(uint8_t * c);
+    void poll(void);
+    void write(const char * data, uint32_t N);
+    void print(const char * fmt, ...) __attribute__((format(printf, 2, 3)));
+    void capture(Console_Capture_T handler);
+    bool session(void);
+    bool idle(void);
+    void close_session(void);
+};
+
+#endif
@@ -0,0 +1,41 @@
+#include "crc32.h"
+
+/* nibble table for the reflected 0x04C11DB7 polynomial */
+static const uint32_t crc32_table[16] = {
+    0x00000000, 0x1DB71064, 0x3B6E20C8, 0x26D930AC,
+    0x76DC4190, 0x6B6B51F4, 0x4DB26158, 0x5005713C,
+    0xEDB88320, 0xF00F9344, 0xD6D6A3E8, 0xCB61B38C,
+    0x9B64C2B0, 0x86D3D2D4, 0xA00AE278, 0xBDBDF21C,
+};
+
+Crc32_T::Crc32_T(void)
+{
+    reset();
+}
+
+void Crc32_T::reset(void)
+{
+    m_crc = 0xFFFFFFFF;
+}
+
+void Crc32_T::update(const uint8_t * data, uint32_t N)
+{
+    uint32_t crc = m_crc;
+
+    for (uint32_t i = 0; i < N; i++) {
+        crc ^= data[i];
+        crc = (crc >> 4) ^ crc32_table[crc & 0x0F];
+        crc = (crc >> 4) ^ crc32_table[crc & 0x0F];
+    }
+
+    m_crc = crc;
+}
+
+/**
+ * @brief   crc of everything fed so far
+ * @note    the context keeps running, more data can still be added afterwards
+ */
+uint32_t Crc32_T::finalize(void)
+{
+    return m_crc ^ 0xFFFFFFFF;
+}
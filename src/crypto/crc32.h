@@ -0,0 +1,18 @@
+#ifndef CRC32_H_
+#define CRC32_H_
+
+#include <stdint.h>
+
+/* crc-32 (ieee 802.3, same as zlib), fed as data arrives */
+class Crc32_T
+{
+private:
+    uint32_t m_crc;
+public:
+    Crc32_T(void);
+    void reset(void);
+    void update(const uint8_t * data, uint32_t N);
+    uint32_t finalize(void);
+};
+
+#endif
@@ -0,0 +1,24 @@
+#ifndef SHA256_H_
+#define SHA256_H_
+
+#include <stdint.h>
+
+#define SHA256_DIGEST_SIZE 32
+
+/* sha-256, fed as data arrives */
+class Sha256_T
+{
+private:
+    uint32_t m_state[8];
+    uint64_t m_length;
+    uint8_t m_block[64];
+    uint32_t m_fill;
+    void m_compress(const uint8_t * block);
+public:
+    Sha256_T(void);
+    void reset(void);
+    void update(const uint8_t * data, uint32_t N);
+    void finalize(uint8_t digest[SHA256_DIGEST_SIZE]);
+};
+
+#endif
@@ -1,4 +1,11 @@
 #include "w25q.h"
+#include "panic.h"
+#include "errors.h"
+#include "qspi.h"
+#include "journal.h"
+#include "mdma.h"
+#include "cycles.h"
+#include <string.h>
 
 extern QSPI_HandleTypeDef hqspi;
 
@@ -18,14 +25,17 @@ void Flash_T::m_reset(void)
 	else
 		cmd.InstructionMode = QSPI_INSTRUCTION_1_LINE;
 	
-	if(HAL_QSPI_Command(&hqspi, &cmd, 100) != HAL_OK)
-		while(1);
+	if(qspi_command(&hqspi, &cmd, 100) != HAL_OK)
+		panic(ERR_PANIC);
 	
-	m_wait();
+	m_wait(W25Q_WAIT_REGISTER_US, W25Q_BACKOFF_REGISTER_US);
 	cmd.Instruction = 0x99;
-	if(HAL_QSPI_Command(&hqspi, &cmd, 100) != HAL_OK)
-		while(1);
+	if(qspi_command(&hqspi, &cmd, 100) != HAL_OK)
+		panic(ERR_PANIC);
 
+	//the reset drops qpi mode, the chip is ready again after 30us
+	m_QSPI_mode = SPI;
+	HAL_Delay(1);
 }
 
 bool Flash_T::m_write_enable(void)
@@ -39,7 +49,7 @@ bool Flash_T::m_write_enable(void)
 		cmd.InstructionMode = QSPI_INSTRUCTION_4_LINES;
 	else
 		cmd.InstructionMode = QSPI_INSTRUCTION_1_LINE;
-	if(HAL_QSPI_Command(&hqspi, &cmd, 100) != HAL_OK)
+	if(qspi_command(&hqspi, &cmd, 100) != HAL_OK)
 		return false;
 	cfg.Match = 0x02;
 	cfg.Mask = 0x02;
@@ -55,23 +65,96 @@ bool Flash_T::m_write_enable(void)
 	
 	cmd.NbData = 1;
 	
-	if(HAL_QSPI_AutoPolling(&hqspi, &cmd, &cfg, 100) != HAL_OK){
+	if(qspi_autopolling(&hqspi, &cmd, &cfg, 100) != HAL_OK){
 		return false;
 	}
 	return true;
 }
 
+//lines of the instruction phase and of everything in qpi mode
+Qspi_Lines_T Flash_T::m_lines(void)
+{
+	return m_QSPI_mode == QSPI ? QSPI_LINES_4 : QSPI_LINES_1;
+}
+
+static bool flash_id_valid(uint16_t id)
+{
+	return id != 0x0000 && id != 0xFFFF;
+}
+
+/**
+ * @brief	capacity of the detected chip
+ * @param	none
+ * @retval	size in bytes, 0 without a chip
+ * @note	the w25q device ids count up from 0x13 for 1MB, so the low byte of
+ * 			the 0x90 id is log2 of the size minus one
+ */
+uint32_t Flash_T::size(void)
+{
+	uint8_t device = m_id & 0xFF;
+
+	if(!flash_id_valid(m_id) || device > 0x1E)
+		return 0;
+	return 1UL << (device + 1);
+}
+
+/**
+ * @brief	check that N bytes from address lie inside the chip
+ * @note	the chip ignores the address bits above its size and the QUADSPI
+ * 			wraps at the end of its window, both would land at the start
+ */
+bool Flash_T::m_in_range(uint32_t address, uint32_t N)
+{
+	uint32_t capacity = size();
+
+	return address < capacity && N <= capacity - address;
+}
+
 /**
- * @brief	exit quad mode, set member QSPI_mode to SPI(false)
+ * @brief	leave qpi mode, set member QSPI_mode to SPI(false)
  * @param	none
+ * @retval	true if the chip is in spi mode afterwards
+ * @note	does nothing when the chip is already in spi mode, 0xFF on a
+ * 			single line would be taken as a continuous read mode reset
  */
-void Flash_T::m_exit_quad_mode(void)
+bool Flash_T::exit_qpi_mode(void)
 {
 	QSPI_CommandTypeDef cmd = {0};
+
+	if(m_memory_mapped)
+		return false;
+	if(m_QSPI_mode == SPI)
+		return true;
+
 	cmd.InstructionMode = QSPI_INSTRUCTION_4_LINES;
-    cmd.Instruction = 0xFF;
-    HAL_QSPI_Command(&hqspi, &cmd, 100);
+	cmd.Instruction = 0xFF;
+	if(qspi_command(&hqspi, &cmd, 100) != HAL_OK)
+		return false;
+
+	m_QSPI_mode = SPI;
+	return true;
+}
+
+/**
+ * @brief	find out which mode the chip is in after a reset of the mcu only
+ * @param	none
+ * @retval	true if the chip answered READ ID in one of the modes
+ * @note	the chip keeps qpi mode across a soft reset while the driver starts
+ * 			out in spi mode. if neither mode answers an exit is sent blindly
+ * 			and spi mode is assumed
+ */
+bool Flash_T::m_detect_mode(void)
+{
 	m_QSPI_mode = SPI;
+	if(flash_id_valid(m_readJEDECID()))
+		return true;
+
+	m_QSPI_mode = QSPI;
+	if(flash_id_valid(m_readJEDECID()))
+		return true;
+
+	exit_qpi_mode();
+	return false;
 }
 
 /**
@@ -82,6 +165,11 @@ void Flash_T::m_exit_quad_mode(void)
 void Flash_T::m_set_quad_mode(void)
 {
 	uint8_t tmp = 0;
+
+	//0x38 is only understood in spi mode
+	if(m_QSPI_mode == QSPI)
+		return;
+
 	m_read_register(&tmp, 2);
 	if((tmp & 0x2) == 0)
 	{
@@ -89,15 +177,17 @@ void Flash_T::m_set_quad_mode(void)
 		m_write_register(tmp, 2);
 	}
 	if(((tmp >> 1) & 0x1) != 1)
-		while(1);
+		panic(ERR_PANIC);
+	//non volatile, still set after leaving qpi mode again
+	m_quad_enabled = true;
 
 	//enter quad mode
 	QSPI_CommandTypeDef cmd = {0};
 	cmd.Instruction = 0x38;
 	cmd.InstructionMode = QSPI_INSTRUCTION_1_LINE;
     cmd.AddressSize = QSPI_ADDRESS_24_BITS;
-	if(HAL_QSPI_Command(&hqspi, &cmd, 100) != HAL_OK)
-		while(1);
+	if(qspi_command(&hqspi, &cmd, 100) != HAL_OK)
+		panic(ERR_PANIC);
 	m_QSPI_mode = QSPI;
 
 	//set ReadParam
@@ -105,11 +195,11 @@ void Flash_T::m_set_quad_mode(void)
     cmd.Instruction = 0xC0;
     cmd.DataMode = QSPI_DATA_4_LINES;
     cmd.NbData = 1;
-	tmp = 0x03 << 4;
+	tmp = ((m_dummy_cycles / 2) - 1) << 4;
 	m_write_enable();
-	if(HAL_QSPI_Command(&hqspi, &cmd, 100) == HAL_OK)
+	if(qspi_command(&hqspi, &cmd, 100) == HAL_OK)
     {
-        HAL_QSPI_Transmit(&hqspi, &tmp, 100);
+        qspi_transmit(&hqspi, &tmp, 100);
     }
 }
 
@@ -130,14 +220,17 @@ uint16_t Flash_T::m_readJEDECID(void)
 	else
 		{cmd.InstructionMode = QSPI_INSTRUCTION_1_LINE;cmd.DataMode = QSPI_DATA_1_LINE;}
 	cmd.Address = 0;
-	cmd.AddressMode = QSPI_ADDRESS_4_LINES;
+	if(m_QSPI_mode == QSPI)
+		cmd.AddressMode = QSPI_ADDRESS_4_LINES;
+	else
+		cmd.AddressMode = QSPI_ADDRESS_1_LINE;
 	cmd.AddressSize = QSPI_ADDRESS_24_BITS;		
 	
 	cmd.NbData = 2;
-	if(HAL_QSPI_Command(&hqspi, &cmd, 100) != HAL_OK)
+	if(qspi_command(&hqspi, &cmd, 100) != HAL_OK)
 		return HAL_ERROR;
 	
-	if(HAL_QSPI_Receive(&hqspi, tmp, 100) != HAL_OK)
+	if(qspi_receive(&hqspi, tmp, 100) != HAL_OK)
 		return HAL_ERROR;
 	ret |= tmp[0] << 8;
 	ret |= tmp[1] << 0;
@@ -164,10 +257,10 @@ bool Flash_T::m_read_register(uint8_t * rbuffer, uint16_t RegisterN)
 	cmd.NbData = 1;
     cmd.AddressSize = QSPI_ADDRESS_24_BITS;
 	
-	if(HAL_QSPI_Command(&hqspi, &cmd, 100) != HAL_OK)
+	if(qspi_command(&hqspi, &cmd, 100) != HAL_OK)
 		return false;
 	
-	if(HAL_QSPI_Receive(&hqspi, rbuffer, 100) != HAL_OK)
+	if(qspi_receive(&hqspi, rbuffer, 100) != HAL_OK)
 		return false;
 	
 	return true;
@@ -197,90 +290,177 @@ bool Flash_T::m_write_register(uint8_t data, uint16_t RegisterN)
 	cmd.NbData = 1;
 	
     cmd.AddressSize = QSPI_ADDRESS_24_BITS;
-	if(HAL_QSPI_Command(&hqspi, &cmd, 10) != HAL_OK)
+	if(qspi_command(&hqspi, &cmd, 10) != HAL_OK)
 		return false;
 	uint8_t tmp = data;
-	if(HAL_QSPI_Transmit(&hqspi, &tmp, 1000) != HAL_OK)
+	if(qspi_transmit(&hqspi, &tmp, 1000) != HAL_OK)
 		return false;
 	return true;
 
 }
 
-bool Flash_T::m_wait(void)
+/*
+ * sleeps until the next tick when interrupts are on and the wait is long
+ * enough for it, spins otherwise. the bootloader api calls in with all
+ * interrupts masked, where the tick doesn't run
+ */
+static void flash_backoff(uint32_t us)
 {
-	QSPI_CommandTypeDef cmd = {0};
-	QSPI_AutoPollingTypeDef cfg = {0};
-	if(m_QSPI_mode == QSPI)
-		{cmd.InstructionMode = QSPI_INSTRUCTION_4_LINES;cmd.DataMode = QSPI_DATA_4_LINES;}
-	else
-		{cmd.InstructionMode = QSPI_INSTRUCTION_1_LINE;cmd.DataMode = QSPI_DATA_1_LINE;}
-	cmd.Instruction = 0x05; //read register 1
-	cmd.NbData = 1; //read one byte
-    cmd.AddressSize = QSPI_ADDRESS_24_BITS;
+	uint32_t start = cycles_now();
 
-	//mask setting
-	cfg.Mask = 0x01; //detect the busy bit
-	cfg.Match = 0x00; //device is idle if bit busy is set as 0
-	cfg.AutomaticStop = QSPI_AUTOMATIC_STOP_ENABLE; //stop sending cmd if match
-	cfg.Interval = 0x10; //time between two send
-	cfg.MatchMode = QSPI_MATCH_MODE_AND; //don't care when detect only one bit
-	cfg.StatusBytesSize = 1; //one byte 
-	if(HAL_QSPI_AutoPolling(&hqspi, &cmd, &cfg, 1000) != HAL_OK)
-		return false;
-	return true;
+	while(cycles_us_since(start) < us)
+	{
+		if(__get_PRIMASK() == 0 && us - cycles_us_since(start) >= 1000)
+			__WFI();
+	}
+}
+
+/**
+ * @brief	wait for the busy bit to clear
+ * @param	timeout		give up after this many us
+ * @param	backoff_max	longest pause between two status reads in us
+ * @retval	true once the chip is idle
+ * @note	the status is read back to back for the first W25Q_POLL_TIGHT_US,
+ * 			which covers a page program, then the pauses double up to
+ * 			backoff_max so a long erase doesn't keep the bus and the cpu busy
+ */
+bool Flash_T::m_wait(uint32_t timeout, uint32_t backoff_max)
+{
+	uint32_t start = cycles_now();
+	uint32_t backoff = W25Q_POLL_BACKOFF_MIN_US;
+	uint8_t status = 0;
+
+	while(1)
+	{
+		if(!m_read_register(&status, 1))
+			return false;
+		if((status & 0x01) == 0)
+		{
+#ifdef FLASH_FAULTS
+			uint32_t extra = m_fault_busy();
+			if(cycles_us_since(start) + extra > timeout)
+				return false;
+			flash_backoff(extra);
+#endif
+			return true;
+		}
+
+		uint32_t elapsed = cycles_us_since(start);
+		if(elapsed > timeout)
+			return false;
+		if(elapsed < W25Q_POLL_TIGHT_US)
+			continue;
+
+		flash_backoff(backoff);
+		if(backoff < backoff_max)
+			backoff = backoff * 2 < backoff_max ? backoff * 2 : backoff_max;
+	}
 }
 
 Flash_T::Flash_T(void)
 {
 	m_QSPI_mode = SPI;
+	m_memory_mapped = false;
+	m_quad_enabled = false;
 	m_id = 0;
+	m_dummy_cycles = 8;
+#ifdef FLASH_FAULTS
+	set_faults(0, 0, 0);
+#endif
 }
 
 void Flash_T::init(void)
 {
-	m_exit_quad_mode();
+	m_detect_mode();
 	m_reset();
 	m_set_quad_mode();
 	m_id = m_readJEDECID();
+
+	//a chip that doesn't answer gets one power cycle before giving up
+	if(!flash_id_valid(m_id))
+		power_cycle();
+}
+
+/**
+ * @brief	cut the flash supply and bring the chip up again from scratch
+ * @param	none
+ * @retval	true if the chip answers with a valid id afterwards
+ * @note	without a power switch configured this is only a software reset
+ */
+bool Flash_T::power_cycle(void)
+{
+	uint32_t slot = journal_begin(JOURNAL_OP_POWER_CYCLE, 0, 0);
+	bool ok = m_power_cycle();
+	journal_end(slot, ok);
+	return ok;
+}
+
+bool Flash_T::m_power_cycle(void)
+{
+	memory_unmap();
+
+	qspi_flash_power_off();
+	qspi_flash_power_on();
+
+	//without a power switch the chip may still be in qpi mode
+	m_detect_mode();
+	m_reset();
+	m_set_quad_mode();
+	m_id = m_readJEDECID();
+
+	return flash_id_valid(m_id);
 }
 
 bool Flash_T::read_N_bytes(uint32_t N, uint32_t address, uint8_t * rbuffer)
 {
 	QSPI_CommandTypeDef cmd = {0};
 	
-	if(address > 0xFFFFFF)
+	if(!m_in_range(address, N))
 		return false;
 	
-	cmd.Instruction = 0x0B;
-	if(m_QSPI_mode == QSPI)
-		cmd.InstructionMode = QSPI_INSTRUCTION_4_LINES;
+	/*
+	 * 0xEB like the memory mapped reads, so the calibration covers them.
+	 * in qpi mode it is 4-4-4 with the mode byte in the first two of the
+	 * configured dummy clocks, in spi mode 1-4-4 with the mode byte and a
+	 * fixed 4 dummy clocks. without the QE bit only 0x0B 1-1-1 works
+	 */
+	if(m_QSPI_mode == QSPI || m_quad_enabled)
+	{
+		qspi_cmd_instruction(&cmd, 0xEB, m_lines());
+		qspi_cmd_address(&cmd, address, QSPI_LINES_4);
+		qspi_cmd_mode_byte(&cmd, 0xFF, QSPI_LINES_4);
+		if(m_QSPI_mode == QSPI)
+			qspi_cmd_dummy(&cmd, m_dummy_cycles - qspi_mode_byte_cycles(QSPI_LINES_4));
+		else
+			qspi_cmd_dummy(&cmd, 4);
+		qspi_cmd_data(&cmd, N, QSPI_LINES_4);
+	}
 	else
-		cmd.InstructionMode = QSPI_INSTRUCTION_1_LINE;
-	
-	cmd.AddressSize = QSPI_ADDRESS_24_BITS;
-	cmd.Address = address;
-	cmd.AddressMode = QSPI_ADDRESS_4_LINES;
-	
-	cmd.DataMode = QSPI_DATA_4_LINES;
-	cmd.NbData = N;
-	
-	cmd.DummyCycles = 8;
+	{
+		qspi_cmd_instruction(&cmd, 0x0B, QSPI_LINES_1);
+		qspi_cmd_address(&cmd, address, QSPI_LINES_1);
+		qspi_cmd_dummy(&cmd, 8);
+		qspi_cmd_data(&cmd, N, QSPI_LINES_1);
+	}
 	
-	if(HAL_QSPI_Command(&hqspi, &cmd, 100) != HAL_OK)
+	if(qspi_command(&hqspi, &cmd, 100) != HAL_OK)
 		return false;
-	if(HAL_QSPI_Receive(&hqspi, rbuffer, 100) != HAL_OK)
+	if(qspi_receive(&hqspi, rbuffer, 100) != HAL_OK)
 		return false;
+#ifdef FLASH_FAULTS
+	m_fault_read(rbuffer, N);
+#endif
 	
 	return true;
 }
 
-bool Flash_T::write_N_bytes(uint32_t N, uint32_t address, uint8_t * sbuffer)
+bool Flash_T::m_write_N_bytes(uint32_t N, uint32_t address, uint8_t * sbuffer)
 {
 	QSPI_CommandTypeDef cmd = {0};
 	uint32_t end_addr, current_addr = 0x00, current_size;
 	uint8_t * current_buffer = sbuffer;
 	
-	if(address > 0xFFFFFF) //detect if address bigger than max address value
+	if(!m_in_range(address, N)) //detect if the write runs past the end of the chip
 		return false;
 	while(current_addr <= address){ //current address increats until bigger than the passed address
 		current_addr += 0x100; //increment is a page 256 bytes
@@ -313,13 +493,22 @@ bool Flash_T::write_N_bytes(uint32_t N, uint32_t address, uint8_t * sbuffer)
 	
 	do
 	{
+#ifdef FLASH_FAULTS
+		if(m_fault_write())
+			return false;
+#endif
 		m_write_enable();
   		m_set_quad_mode();
 		
-		if(HAL_QSPI_Command(&hqspi, &cmd, 100) != HAL_OK)
+		if(qspi_command(&hqspi, &cmd, 100) != HAL_OK)
+			return false;
+		if(qspi_transmit(&hqspi, current_buffer, 10000) != HAL_OK)
 			return false;
-		if(HAL_QSPI_Transmit(&hqspi, current_buffer, 10000) != HAL_OK)
+		//the next write enable would be taken while the page is still programming
+		if(!m_wait(W25Q_WAIT_PAGE_US, W25Q_BACKOFF_PAGE_US))
 			return false;
+		if(W25Q_PAGE_DELAY_MS)
+			flash_backoff(W25Q_PAGE_DELAY_MS * 1000);
 		current_addr += current_size;
 		current_buffer += current_size;
 		
@@ -336,10 +525,12 @@ bool Flash_T::write_N_bytes(uint32_t N, uint32_t address, uint8_t * sbuffer)
 	return true;
 }
 
-bool Flash_T::sector_erase(uint32_t start, uint32_t end)
+bool Flash_T::m_sector_erase(uint32_t start, uint32_t end)
 {
 	QSPI_CommandTypeDef cmd = {0};
 	uint16_t sector_start = 0, sector_end = 0;
+	if(start > end || !m_in_range(end, 1))
+		return false;
 	sector_start = start / 4096; //start is the num of the first sector
 	sector_end = end / 4096; //end is the num of the last sector
 	cmd.Instruction = 0x20;	
@@ -352,37 +543,215 @@ bool Flash_T::sector_erase(uint32_t start, uint32_t end)
 	{
 		m_write_enable();
 		cmd.Address = sector_start * 4096; //sector increse
-		if(HAL_QSPI_Command(&hqspi, &cmd, 100) != HAL_OK)
+		if(qspi_command(&hqspi, &cmd, 100) != HAL_OK)
 			return false;
 		sector_start++;
-		m_wait();
+		if(!m_wait(W25Q_WAIT_SECTOR_US, W25Q_BACKOFF_SECTOR_US))
+			return false;
+		if(W25Q_ERASE_DELAY_MS)
+			flash_backoff(W25Q_ERASE_DELAY_MS * 1000);
 	}while(sector_start <= sector_end);
 	
 	return true;
 }
 
+/**
+ * @brief	program N bytes, logged in the flash journal
+ * @param	N		number of bytes
+ * @param	address	flash offset of the first byte
+ * @param	sbuffer	data to program
+ */
+bool Flash_T::write_N_bytes(uint32_t N, uint32_t address, uint8_t * sbuffer)
+{
+	uint32_t slot = journal_begin(JOURNAL_OP_WRITE, address, N);
+	bool ok = m_write_N_bytes(N, address, sbuffer);
+	journal_end(slot, ok);
+	return ok;
+}
+
+/**
+ * @brief	erase the sectors from start to end, logged in the flash journal
+ * @param	start	an address inside the first sector
+ * @param	end		an address inside the last sector
+ */
+bool Flash_T::sector_erase(uint32_t start, uint32_t end)
+{
+	uint32_t slot = journal_begin(JOURNAL_OP_ERASE, start, end - start + 1);
+	bool ok = m_sector_erase(start, end);
+	journal_end(slot, ok);
+	return ok;
+}
+
+/**
+ * @brief	erase the whole chip, logged in the flash journal
+ * @param	progress	called every W25Q_PROGRESS_MS with an estimate, may be NULL
+ * @retval	true once the chip reports idle again
+ */
+bool Flash_T::chip_erase(Flash_Progress_T progress)
+{
+	uint32_t slot = journal_begin(JOURNAL_OP_ERASE, 0, size());
+	bool ok = m_chip_erase(progress);
+	journal_end(slot, ok);
+	return ok;
+}
+
+/*
+ * up to the typical erase time the estimate runs linearly to 90%, after that
+ * it creeps towards 99% at the maximum time. 100% is only reported when done
+ */
+static uint32_t chip_erase_estimate(uint32_t elapsed)
+{
+	if(elapsed < W25Q_CHIP_ERASE_TYP_MS)
+		return elapsed * 90 / W25Q_CHIP_ERASE_TYP_MS;
+	if(elapsed < W25Q_CHIP_ERASE_MAX_MS)
+		return 90 + (elapsed - W25Q_CHIP_ERASE_TYP_MS) * 9 / (W25Q_CHIP_ERASE_MAX_MS - W25Q_CHIP_ERASE_TYP_MS);
+	return 99;
+}
+
+bool Flash_T::m_chip_erase(Flash_Progress_T progress)
+{
+	QSPI_CommandTypeDef cmd = {0};
+	uint8_t status = 0;
+
+	if(m_memory_mapped || size() == 0)
+		return false;
+
+	m_write_enable();
+	qspi_cmd_instruction(&cmd, 0xC7, m_lines());
+	if(qspi_command(&hqspi, &cmd, 100) != HAL_OK)
+		return false;
+
+	//polled by hand, the autopolling timeout can't cover tCE and report progress
+	uint32_t start = HAL_GetTick();
+	uint32_t reported = start;
+	do
+	{
+		uint32_t elapsed = HAL_GetTick() - start;
+
+		//a chip still busy well past the maximum time is not coming back
+		if(elapsed > 2 * W25Q_CHIP_ERASE_MAX_MS)
+			return false;
+
+		if(progress != NULL && HAL_GetTick() - reported >= W25Q_PROGRESS_MS)
+		{
+			reported = HAL_GetTick();
+			progress(chip_erase_estimate(elapsed), elapsed);
+		}
+
+		HAL_Delay(10);
+		if(!m_read_register(&status, 1))
+			return false;
+	}while(status & 0x01);
+
+	if(progress != NULL)
+		progress(100, HAL_GetTick() - start);
+	return true;
+}
+
 /**
  * @brief	enter memory map mode
  * @param	none
- * @note	you can choose 0xEB or 0x0B for read command 
+ * @note	you can choose 0xEB or 0x0B for read command. 0xEB takes the M7-M0
+ * 			byte in the first two of its dummy clocks, 0xFF keeps the chip out
+ * 			of continuous read mode so the next command is decoded normally
  */
 void Flash_T::memory_map(void)
 {
 	QSPI_CommandTypeDef cmd = {0};
 	QSPI_MemoryMappedTypeDef cfg = {0};
 
-	cmd.InstructionMode = QSPI_INSTRUCTION_4_LINES;
-	cmd.Instruction = 0xEB; //quad fast read
-	cmd.AddressMode = QSPI_ADDRESS_4_LINES;
-	cmd.AddressSize = QSPI_ADDRESS_24_BITS;
-	cmd.AlternateByteMode = QSPI_ALTERNATE_BYTES_NONE;
-	cmd.DataMode = QSPI_DATA_4_LINES;
-	cmd.DummyCycles = 8;
+	qspi_cmd_instruction(&cmd, 0xEB, QSPI_LINES_4); //quad fast read
+	qspi_cmd_address(&cmd, 0, QSPI_LINES_4);
+	qspi_cmd_mode_byte(&cmd, 0xFF, QSPI_LINES_4);
+	qspi_cmd_dummy(&cmd, m_dummy_cycles - qspi_mode_byte_cycles(QSPI_LINES_4));
+	qspi_cmd_data(&cmd, 0, QSPI_LINES_4);
 
 	cfg.TimeOutActivation = QSPI_TIMEOUT_COUNTER_DISABLE;
   	cfg.TimeOutPeriod = 0;
  
 	if (HAL_QSPI_MemoryMapped(&hqspi, &cmd, &cfg) != HAL_OK) {
-        while (1);
+        panic(ERR_PANIC);
+	}
+	m_memory_mapped = true;
+}
+
+/**
+ * @brief	leave memory map mode so indirect commands can be issued again
+ * @param	none
+ */
+void Flash_T::memory_unmap(void)
+{
+	if(!m_memory_mapped)
+		return;
+
+	HAL_QSPI_Abort(&hqspi);
+	m_memory_mapped = false;
+}
+
+/**
+ * @brief	read N bytes regardless of the current peripheral mode
+ * @param	N		number of bytes to read
+ * @param	address	flash offset of the first byte
+ * @param	rbuffer	destination buffer
+ * @note	in memory mapped mode indirect commands are rejected by the QUADSPI,
+ * 			so the data is copied from the 0x90000000 window instead. the copy is
+ * 			done by the mdma, which reads the window past the d-cache in bursts.
+ * 			only when it fails the cpu copies, with the d-cache lines covering the
+ * 			range invalidated first, otherwise a copy made after the flash was
+ * 			reprogrammed may return stale data
+ */
+bool Flash_T::read_memory_mapped(uint32_t N, uint32_t address, uint8_t * rbuffer)
+{
+	if(!m_memory_mapped)
+		return read_N_bytes(N, address, rbuffer);
+
+	if(!m_in_range(address, N))
+		return false;
+
+	if(!mdma_copy_from_xip(rbuffer, address, N))
+	{
+		SCB_InvalidateDCache_by_Addr((void *)(QSPI_BASE + address), N);
+		memcpy(rbuffer, (const void *)(QSPI_BASE + address), N);
 	}
+#ifdef FLASH_FAULTS
+	m_fault_read(rbuffer, N);
+#endif
+	return true;
+}
+
+/**
+ * @brief	change the dummy cycles of the quad fast reads (0x0B/0xEB)
+ * @param	cycles	2, 4, 6 or 8. the datasheet rates them for 50, 80, 104 and 133MHz
+ * @retval	true if the set read parameters command went out
+ * @note	the chip and the controller have to agree, so this is only allowed
+ * 			outside memory mapped mode
+ */
+bool Flash_T::set_dummy_cycles(uint8_t cycles)
+{
+	QSPI_CommandTypeDef cmd = {0};
+	uint8_t tmp;
+
+	if(m_memory_mapped || m_QSPI_mode != QSPI)
+		return false;
+	if(cycles < 2 || cycles > 8 || (cycles & 1))
+		return false;
+
+	cmd.InstructionMode = QSPI_INSTRUCTION_4_LINES;
+	cmd.Instruction = 0xC0;
+	cmd.DataMode = QSPI_DATA_4_LINES;
+	cmd.NbData = 1;
+	tmp = ((cycles / 2) - 1) << 4;
+	m_write_enable();
+	if(qspi_command(&hqspi, &cmd, 100) != HAL_OK)
+		return false;
+	if(qspi_transmit(&hqspi, &tmp, 100) != HAL_OK)
+		return false;
+
+	m_dummy_cycles = cycles;
+	return true;
+}
+
+uint8_t Flash_T::dummy_cycles(void)
+{
+	return m_dummy_cycles;
 }
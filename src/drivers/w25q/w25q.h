@@ -1,5 +1,6 @@
 #include "stm32h7xx_hal.h"
 #include "stm32h7xx_hal_qspi.h"
+#include "qspi.h"
 
 #define W25Q64_H
 #ifdef W25Q64_H
@@ -7,26 +8,102 @@
 #define QSPI true
 #define SPI false
 
+/* tCE of the w25q64jv, the chip gives no progress while it is busy */
+#define W25Q_CHIP_ERASE_TYP_MS		20000
+#define W25Q_CHIP_ERASE_MAX_MS		100000
+#define W25Q_PROGRESS_MS			500
+
+/*
+ * rest after each page program and sector erase, for boards whose supply
+ * can't feed back to back quad programs, e.g. -DW25Q_PAGE_DELAY_MS=2.
+ * the driver never overlaps operations, so there is one at a time anyway.
+ * the rest doesn't depend on the tick, these also run with interrupts masked
+ */
+#ifndef W25Q_PAGE_DELAY_MS
+#define W25Q_PAGE_DELAY_MS			0
+#endif
+#ifndef W25Q_ERASE_DELAY_MS
+#define W25Q_ERASE_DELAY_MS			0
+#endif
+
+/*
+ * busy polling, see Flash_T::m_wait(). timeouts are well above the maximum
+ * tPP, tSE and tW of the w25q64jv, the backoff caps keep the overshoot after
+ * a typical operation small
+ */
+#define W25Q_POLL_TIGHT_US			100
+#define W25Q_POLL_BACKOFF_MIN_US	20
+#define W25Q_WAIT_PAGE_US			10000
+#define W25Q_BACKOFF_PAGE_US		200
+#define W25Q_WAIT_SECTOR_US			1000000
+#define W25Q_BACKOFF_SECTOR_US		5000
+#define W25Q_WAIT_REGISTER_US		50000
+#define W25Q_BACKOFF_REGISTER_US	1000
+
+#ifdef FLASH_FAULTS
+/* the fault injector settings, see Flash_T::set_faults(), and what it did so far */
+typedef struct {
+	uint32_t write_one_in;
+	uint32_t flip_one_in;
+	uint32_t busy_max_us;
+	uint32_t writes_failed;
+	uint32_t bits_flipped;
+	uint32_t busy_delayed;
+} Flash_Faults_T;
+#endif
+
+/* estimated percent done and the time so far, called while a long operation runs */
+typedef void (*Flash_Progress_T)(uint32_t percent, uint32_t elapsed_ms);
+
 class Flash_T
 {
 private:
     bool m_QSPI_mode;
+    bool m_memory_mapped;
+    bool m_quad_enabled;
     uint16_t m_id;
+    uint8_t m_dummy_cycles;
     void m_reset(void);
     bool m_write_enable(void);
-    void m_exit_quad_mode(void);
+    bool m_detect_mode(void);
+    Qspi_Lines_T m_lines(void);
+    bool m_in_range(uint32_t address, uint32_t N);
     void m_set_quad_mode(void);
     uint16_t m_readJEDECID(void);
     bool m_read_register(uint8_t * rbuffer, uint16_t RegisterN);
     bool m_write_register(uint8_t data, uint16_t RegisterN);
-    bool m_wait(void);
+    bool m_wait(uint32_t timeout, uint32_t backoff_max);
+    bool m_write_N_bytes(uint32_t N, uint32_t address, uint8_t * sbuffer);
+    bool m_sector_erase(uint32_t start, uint32_t end);
+    bool m_power_cycle(void);
+    bool m_chip_erase(Flash_Progress_T progress);
+#ifdef FLASH_FAULTS
+    Flash_Faults_T m_faults;
+    uint32_t m_fault_seed;
+    uint32_t m_fault_random(void);
+    bool m_fault_write(void);
+    void m_fault_read(uint8_t * rbuffer, uint32_t N);
+    uint32_t m_fault_busy(void);
+#endif
 public:
     Flash_T(void);
     void init(void);
+    bool power_cycle(void);
+    bool exit_qpi_mode(void);
+    uint32_t size(void);
     bool read_N_bytes(uint32_t N, uint32_t address, uint8_t * rbuffer);
     bool write_N_bytes(uint32_t N, uint32_t address, uint8_t * sbuffer);
     bool sector_erase(uint32_t start, uint32_t end);
+    bool chip_erase(Flash_Progress_T progress);
     void memory_map(void);
+    void memory_unmap(void);
+    bool read_memory_mapped(uint32_t N, uint32_t address, uint8_t * rbuffer);
+    bool set_dummy_cycles(uint8_t cycles);
+    uint8_t dummy_cycles(void);
+#ifdef FLASH_FAULTS
+    void set_faults(uint32_t write_one_in, uint32_t flip_one_in, uint32_t busy_max_us);
+    const Flash_Faults_T * faults(void);
+#endif
 };
 
 #endif
@@ -0,0 +1,75 @@
+#include "w25q.h"
+#include "cycles.h"
+
+#ifdef FLASH_FAULTS
+
+/*
+ * fault injection for robustness tests on the bench, shared by the driver
+ * and the stub. the faults are drawn from a xorshift seeded by the cycle
+ * counter, a run isn't meant to be repeatable
+ */
+
+/**
+ * @brief	configure the injected faults and clear the counters
+ * @param	write_one_in	one in this many page programs fails, 0 for never
+ * @param	flip_one_in		one in this many reads gets a bit flipped, 0 for never
+ * @param	busy_max_us		the busy bit stays set up to this much longer, 0 for never
+ */
+void Flash_T::set_faults(uint32_t write_one_in, uint32_t flip_one_in, uint32_t busy_max_us)
+{
+	m_faults.write_one_in = write_one_in;
+	m_faults.flip_one_in = flip_one_in;
+	m_faults.busy_max_us = busy_max_us;
+	m_faults.writes_failed = 0;
+	m_faults.bits_flipped = 0;
+	m_faults.busy_delayed = 0;
+	m_fault_seed = cycles_now() | 1;
+}
+
+const Flash_Faults_T * Flash_T::faults(void)
+{
+	return &m_faults;
+}
+
+uint32_t Flash_T::m_fault_random(void)
+{
+	m_fault_seed ^= m_fault_seed << 13;
+	m_fault_seed ^= m_fault_seed >> 17;
+	m_fault_seed ^= m_fault_seed << 5;
+	return m_fault_seed;
+}
+
+//true if the page program about to start is to fail
+bool Flash_T::m_fault_write(void)
+{
+	if(m_faults.write_one_in == 0 || m_fault_random() % m_faults.write_one_in != 0)
+		return false;
+
+	m_faults.writes_failed++;
+	return true;
+}
+
+//flips one bit somewhere in the data just read
+void Flash_T::m_fault_read(uint8_t * rbuffer, uint32_t N)
+{
+	if(N == 0 || m_faults.flip_one_in == 0 || m_fault_random() % m_faults.flip_one_in != 0)
+		return;
+
+	uint32_t bit = m_fault_random() % (N * 8);
+	rbuffer[bit / 8] ^= 1 << (bit % 8);
+	m_faults.bits_flipped++;
+}
+
+//how much longer the chip is to look busy, in us
+uint32_t Flash_T::m_fault_busy(void)
+{
+	if(m_faults.busy_max_us == 0)
+		return 0;
+
+	uint32_t us = m_fault_random() % (m_faults.busy_max_us + 1);
+	if(us)
+		m_faults.busy_delayed++;
+	return us;
+}
+
+#endif
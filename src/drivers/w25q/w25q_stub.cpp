@@ -0,0 +1,142 @@
+#include "w25q.h"
+#include "journal.h"
+#include <string.h>
+
+/*
+ * memory backed replacement of the w25q driver for renode/qemu runs.
+ * the emulator maps plain memory at the quadspi window, so the "flash" is
+ * accessed directly at 0x90000000 and memory mapped mode is always on.
+ */
+
+#define W25Q_STUB_SIZE 0x800000
+
+Flash_T::Flash_T(void)
+{
+	m_QSPI_mode = QSPI;
+	m_memory_mapped = true;
+	m_quad_enabled = true;
+	m_id = 0;
+	m_dummy_cycles = 8;
+#ifdef FLASH_FAULTS
+	set_faults(0, 0, 0);
+#endif
+}
+
+void Flash_T::init(void)
+{
+	m_id = 0xEF16; //w25q64 manufacturer and device id
+}
+
+bool Flash_T::power_cycle(void)
+{
+	journal_end(journal_begin(JOURNAL_OP_POWER_CYCLE, 0, 0), true);
+	return true;
+}
+
+uint32_t Flash_T::size(void)
+{
+	return W25Q_STUB_SIZE;
+}
+
+//memory mapped mode is always on, same as the real driver refuses then
+bool Flash_T::exit_qpi_mode(void)
+{
+	return false;
+}
+
+bool Flash_T::read_N_bytes(uint32_t N, uint32_t address, uint8_t * rbuffer)
+{
+	if(address > W25Q_STUB_SIZE - 1 || N > W25Q_STUB_SIZE - address)
+		return false;
+
+	memcpy(rbuffer, (const void *)(QSPI_BASE + address), N);
+#ifdef FLASH_FAULTS
+	m_fault_read(rbuffer, N);
+#endif
+	return true;
+}
+
+bool Flash_T::write_N_bytes(uint32_t N, uint32_t address, uint8_t * sbuffer)
+{
+	uint32_t slot = journal_begin(JOURNAL_OP_WRITE, address, N);
+	bool ok = m_write_N_bytes(N, address, sbuffer);
+	journal_end(slot, ok);
+	return ok;
+}
+
+bool Flash_T::m_write_N_bytes(uint32_t N, uint32_t address, uint8_t * sbuffer)
+{
+	uint8_t * flash = (uint8_t *)(QSPI_BASE + address);
+
+	if(address > W25Q_STUB_SIZE - 1 || N > W25Q_STUB_SIZE - address)
+		return false;
+
+#ifdef FLASH_FAULTS
+	//the memory has no busy bit, so only the programming can fail
+	if(m_fault_write())
+		return false;
+#endif
+
+	//programming can only clear bits, same as the real chip
+	for(uint32_t i = 0; i < N; i++)
+		flash[i] &= sbuffer[i];
+	return true;
+}
+
+bool Flash_T::sector_erase(uint32_t start, uint32_t end)
+{
+	uint32_t slot = journal_begin(JOURNAL_OP_ERASE, start, end - start + 1);
+	bool ok = m_sector_erase(start, end);
+	journal_end(slot, ok);
+	return ok;
+}
+
+bool Flash_T::m_sector_erase(uint32_t start, uint32_t end)
+{
+	uint32_t sector_start = start / 4096;
+	uint32_t sector_end = end / 4096;
+
+	if(start > end || end > W25Q_STUB_SIZE - 1)
+		return false;
+
+	memset((void *)(QSPI_BASE + sector_start * 4096), 0xFF, (sector_end - sector_start + 1) * 4096);
+	return true;
+}
+
+//instant in the emulator, so the only progress report is the final one
+bool Flash_T::chip_erase(Flash_Progress_T progress)
+{
+	uint32_t slot = journal_begin(JOURNAL_OP_ERASE, 0, W25Q_STUB_SIZE);
+
+	memset((void *)QSPI_BASE, 0xFF, W25Q_STUB_SIZE);
+	journal_end(slot, true);
+
+	if(progress != NULL)
+		progress(100, 0);
+	return true;
+}
+
+void Flash_T::memory_map(void)
+{
+	m_memory_mapped = true;
+}
+
+void Flash_T::memory_unmap(void)
+{
+}
+
+bool Flash_T::read_memory_mapped(uint32_t N, uint32_t address, uint8_t * rbuffer)
+{
+	return read_N_bytes(N, address, rbuffer);
+}
+
+bool Flash_T::set_dummy_cycles(uint8_t cycles)
+{
+	m_dummy_cycles = cycles;
+	return true;
+}
+
+uint8_t Flash_T::dummy_cycles(void)
+{
+	return m_dummy_cycles;
+}
@@ -0,0 +1,250 @@
+#include "eol.h"
+#include "crc32.h"
+#include "layout.h"
+#include "w25q.h"
+#include "stm32h7xx_hal.h"
+#include <stdarg.h>
+#include <stdio.h>
+#include <string.h>
+
+#ifdef EOL_TEST
+
+extern Flash_T flash;
+
+struct Eol_Loopback_T {
+    GPIO_TypeDef * out_port;
+    uint16_t out_pin;
+    GPIO_TypeDef * in_port;
+    uint16_t in_pin;
+};
+
+/* closed by an entry without ports */
+static const Eol_Loopback_T eol_loopbacks[] = {
+    EOL_LOOPBACKS
+    {NULL, 0, NULL, 0},
+};
+
+/* not used by the bootloader, so the whole of them can be overwritten */
+struct Eol_Ram_T {
+    const char * name;
+    uint32_t base;
+    uint32_t len;
+};
+
+static const Eol_Ram_T eol_rams[] = {
+    {"axisram", D1_AXISRAM_BASE, 512 * 1024},
+    {"sram1", D2_AHBSRAM_BASE, 288 * 1024},
+};
+
+static uint8_t eol_buffer[LAYOUT_SECTOR_SIZE];
+
+static Crc32_T eol_crc;
+static uint32_t eol_failed;
+
+static void eol_line(Console_T & console, const char * fmt, ...) __attribute__((format(printf, 2, 3)));
+
+static void eol_line(Console_T & console, const char * fmt, ...)
+{
+    char line[128];
+    va_list args;
+
+    va_start(args, fmt);
+    int len = vsnprintf(line, sizeof(line) - 2, fmt, args);
+    va_end(args);
+
+    if (len < 0)
+        return;
+    if ((uint32_t)len >= sizeof(line) - 2)
+        len = sizeof(line) - 3;
+
+    line[len++] = '\r';
+    line[len++] = '\n';
+
+    eol_crc.update((const uint8_t *)line, len);
+    console.write(line, len);
+}
+
+static void eol_result(Console_T & console, bool ok, const char * name, const char * detail)
+{
+    if (!ok)
+        eol_failed++;
+
+    eol_line(console, "%s %s %s", ok ? "PASS" : "FAIL", name, detail);
+}
+
+/* march c- with the cache flushed between the elements, then each word holding its own address */
+static bool eol_ram_test(const Eol_Ram_T * ram, uint32_t * bad)
+{
+    volatile uint32_t * words = (volatile uint32_t *)ram->base;
+    uint32_t count = ram->len / 4;
+    static const uint32_t steps[][2] = {
+        {0x00000000, 0xFFFFFFFF},
+        {0xFFFFFFFF, 0x00000000},
+    };
+
+    for (uint32_t i = 0; i < count; i++)
+        words[i] = 0;
+
+    for (uint32_t pass = 0; pass < 4; pass++) {
+        const uint32_t * step = steps[pass % 2];
+        bool down = pass >= 2;
+
+        SCB_CleanInvalidateDCache();
+
+        for (uint32_t n = 0; n < count; n++) {
+            uint32_t i = down ? count - 1 - n : n;
+
+            if (words[i] != step[0]) {
+                *bad = ram->base + i * 4;
+                return false;
+            }
+            words[i] = step[1];
+        }
+    }
+
+    SCB_CleanInvalidateDCache();
+
+    for (uint32_t i = 0; i < count; i++) {
+        if (words[i] != 0) {
+            *bad = ram->base + i * 4;
+            return false;
+        }
+        words[i] = ram->base + i * 4;
+    }
+
+    SCB_CleanInvalidateDCache();
+
+    for (uint32_t i = 0; i < count; i++) {
+        if (words[i] != ram->base + i * 4) {
+            *bad = ram->base + i * 4;
+            return false;
+        }
+    }
+
+    return true;
+}
+
+static void eol_ram(Console_T & console)
+{
+    char detail[48];
+
+    __HAL_RCC_D2SRAM1_CLK_ENABLE();
+
+    for (const Eol_Ram_T & ram : eol_rams) {
+        uint32_t bad = 0;
+        bool ok = eol_ram_test(&ram, &bad);
+
+        if (ok)
+            snprintf(detail, sizeof(detail), "%s %lu KiB", ram.name, ram.len / 1024);
+        else
+            snprintf(detail, sizeof(detail), "%s at 0x%08lx", ram.name, bad);
+        eol_result(console, ok, "ram", detail);
+    }
+}
+
+/* round trip through the first scratch sector, the calibration keeps the last one */
+static void eol_flash(Console_T & console)
+{
+    char detail[48];
+
+    snprintf(detail, sizeof(detail), "%lu KiB", flash.size() / 1024);
+    eol_result(console, flash.size() != 0, "flash-id", detail);
+    if (flash.size() == 0)
+        return;
+
+    for (uint32_t i = 0; i < sizeof(eol_buffer); i++)
+        eol_buffer[i] = i ^ (i >> 8);
+
+    bool ok = flash.sector_erase(Scratch_T::base, Scratch_T::base) &&
+              flash.write_N_bytes(sizeof(eol_buffer), Scratch_T::base, eol_buffer);
+
+    if (ok) {
+        memset(eol_buffer, 0, sizeof(eol_buffer));
+        ok = flash.read_N_bytes(sizeof(eol_buffer), Scratch_T::base, eol_buffer);
+    }
+
+    for (uint32_t i = 0; ok && i < sizeof(eol_buffer); i++) {
+        if (eol_buffer[i] != (uint8_t)(i ^ (i >> 8))) {
+            snprintf(detail, sizeof(detail), "offset 0x%03lx", i);
+            ok = false;
+        }
+    }
+
+    if (ok)
+        snprintf(detail, sizeof(detail), "scratch sector");
+    eol_result(console, ok, "flash-rw", detail);
+
+    flash.sector_erase(Scratch_T::base, Scratch_T::base);
+}
+
+static void eol_clocks(Console_T & console)
+{
+    char detail[48];
+    uint32_t sysclk = HAL_RCC_GetSysClockFreq();
+
+    snprintf(detail, sizeof(detail), "%lu Hz", sysclk);
+    eol_result(console, __HAL_RCC_GET_FLAG(RCC_FLAG_HSERDY) && sysclk == EOL_SYSCLK_HZ, "sysclk", detail);
+
+    /* the rtc crystal, nothing else runs off it yet */
+    eol_result(console, __HAL_RCC_GET_FLAG(RCC_FLAG_LSERDY), "lse", "32768 Hz");
+}
+
+static bool eol_loopback_level(const Eol_Loopback_T * loop, GPIO_PinState level)
+{
+    HAL_GPIO_WritePin(loop->out_port, loop->out_pin, level);
+    HAL_Delay(1);
+    return HAL_GPIO_ReadPin(loop->in_port, loop->in_pin) == level;
+}
+
+static void eol_gpio(Console_T & console)
+{
+    char detail[48];
+
+    for (uint32_t i = 0; eol_loopbacks[i].out_port != NULL; i++) {
+        const Eol_Loopback_T * loop = &eol_loopbacks[i];
+        GPIO_InitTypeDef config = {0};
+
+        config.Pin = loop->out_pin;
+        config.Mode = GPIO_MODE_OUTPUT_PP;
+        config.Speed = GPIO_SPEED_FREQ_LOW;
+        HAL_GPIO_Init(loop->out_port, &config);
+
+        config.Pin = loop->in_pin;
+        config.Mode = GPIO_MODE_INPUT;
+        config.Pull = GPIO_NOPULL;
+        HAL_GPIO_Init(loop->in_port, &config);
+
+        bool ok = eol_loopback_level(loop, GPIO_PIN_SET) && eol_loopback_level(loop, GPIO_PIN_RESET);
+
+        HAL_GPIO_DeInit(loop->out_port, loop->out_pin);
+        HAL_GPIO_DeInit(loop->in_port, loop->in_pin);
+
+        snprintf(detail, sizeof(detail), "loopback %lu", i);
+        eol_result(console, ok, "gpio", detail);
+    }
+}
+
+/**
+ * @brief   run all tests and print the report
+ * @retval  ERR_OK if every test passed, ERR_EOL_FAILED otherwise
+ */
+Error_T eol_run(Console_T & console)
+{
+    eol_crc.reset();
+    eol_failed = 0;
+
+    eol_line(console, "EOL uid %08lx%08lx%08lx", *(const uint32_t *)(UID_BASE + 8),
+             *(const uint32_t *)(UID_BASE + 4), *(const uint32_t *)UID_BASE);
+
+    eol_clocks(console);
+    eol_flash(console);
+    eol_ram(console);
+    eol_gpio(console);
+
+    eol_line(console, "EOL %s %lu failed", eol_failed ? "FAIL" : "PASS", eol_failed);
+    console.print("EOL crc32 0x%08lx\r\n", eol_crc.finalize());
+
+    return eol_failed ? ERR_EOL_FAILED : ERR_OK;
+}
+
+#endif
@@ -0,0 +1,30 @@
+#ifndef EOL_H_
+#define EOL_H_
+
+#include "console.h"
+#include "errors.h"
+
+/*
+ * end of line test for production, only built with -DEOL_TEST=ON. every test
+ * prints one "PASS <name> ..." or "FAIL <name> ..." line and the report ends
+ * with a crc32 over all lines before it, so the station can tell a complete
+ * log from a truncated one. the report is not signed, there are no device
+ * keys to sign with.
+ */
+
+/* what the rcc setup in bsp/rcc.c is expected to give */
+#define EOL_SYSCLK_HZ   480000000
+
+/*
+ * pairs of pins wired together on the test fixture, as
+ * {out port, out pin, in port, in pin}, entries followed by a comma, e.g.
+ * -DEOL_LOOPBACKS="{GPIOA, GPIO_PIN_0, GPIOA, GPIO_PIN_1},"
+ * the ports have to be among the ones gpio_init() clocks.
+ */
+#ifndef EOL_LOOPBACKS
+#define EOL_LOOPBACKS
+#endif
+
+Error_T eol_run(Console_T & console);
+
+#endif
@@ -0,0 +1,54 @@
+#include "errors.h"
+
+typedef struct {
+    int code;
+    const char *name;
+} Error_Name_T;
+
+static const Error_Name_T error_names[] = {
+    {ERR_OK, "ERR_OK"},
+    {ERR_UNKNOWN_COMMAND, "ERR_UNKNOWN_COMMAND"},
+    {ERR_BAD_ARGUMENT, "ERR_BAD_ARGUMENT"},
+    {ERR_FLASH_ERASE, "ERR_FLASH_ERASE"},
+    {ERR_FLASH_WRITE, "ERR_FLASH_WRITE"},
+    {ERR_FLASH_READ, "ERR_FLASH_READ"},
+    {ERR_FLASH_MISMATCH, "ERR_FLASH_MISMATCH"},
+    {ERR_FLASH_NO_CHIP, "ERR_FLASH_NO_CHIP"},
+    {ERR_FLASH_CALIBRATION, "ERR_FLASH_CALIBRATION"},
+    {ERR_FLASH_NOT_BLANK, "ERR_FLASH_NOT_BLANK"},
+    {ERR_OUT_OF_RANGE, "ERR_OUT_OF_RANGE"},
+    {ERR_UNALIGNED, "ERR_UNALIGNED"},
+    {ERR_BOOT_STACK_OUT_OF_RAM, "ERR_BOOT_STACK_OUT_OF_RAM"},
+    {ERR_BOOT_STACK_UNALIGNED, "ERR_BOOT_STACK_UNALIGNED"},
+    {ERR_BOOT_RESET_OUT_OF_SLOT, "ERR_BOOT_RESET_OUT_OF_SLOT"},
+    {ERR_BOOT_RESET_NOT_THUMB, "ERR_BOOT_RESET_NOT_THUMB"},
+    {ERR_BOOT_RESET_OUT_OF_IMAGE, "ERR_BOOT_RESET_OUT_OF_IMAGE"},
+    {ERR_HEX_SYNTAX, "ERR_HEX_SYNTAX"},
+    {ERR_HEX_CHECKSUM, "ERR_HEX_CHECKSUM"},
+    {ERR_HEX_RECORD, "ERR_HEX_RECORD"},
+    {ERR_MANIFEST_INVALID, "ERR_MANIFEST_INVALID"},
+    {ERR_MANIFEST_HASH, "ERR_MANIFEST_HASH"},
+    {ERR_MANIFEST_FULL, "ERR_MANIFEST_FULL"},
+    {ERR_SELF_CHECK_CRC, "ERR_SELF_CHECK_CRC"},
+    {ERR_PANIC, "ERR_PANIC"},
+    {ERR_FAULT, "ERR_FAULT"},
+    {ERR_EOL_FAILED, "ERR_EOL_FAILED"},
+    {ERR_IMAGE_MAGIC, "ERR_IMAGE_MAGIC"},
+    {ERR_IMAGE_HEADER_CRC, "ERR_IMAGE_HEADER_CRC"},
+    {ERR_IMAGE_LENGTH, "ERR_IMAGE_LENGTH"},
+    {ERR_IMAGE_ENTRY, "ERR_IMAGE_ENTRY"},
+    {ERR_IMAGE_BOARD, "ERR_IMAGE_BOARD"},
+    {ERR_IMAGE_FLASH_SIZE, "ERR_IMAGE_FLASH_SIZE"},
+    {ERR_IMAGE_EXPIRED, "ERR_IMAGE_EXPIRED"},
+    {ERR_IMAGE_TLV, "ERR_IMAGE_TLV"},
+};
+
+const char *error_str(int error)
+{
+    for (uint32_t i = 0; i < sizeof(error_names) / sizeof(error_names[0]); i++) {
+        if (error_names[i].code == error)
+            return error_names[i].name;
+    }
+
+    return "ERR_UNKNOWN";
+}
@@ -0,0 +1,69 @@
+#ifndef ERRORS_H_
+#define ERRORS_H_
+
+#include <stdint.h>
+
+/*
+ * error codes reported to the host, on the console and to applications.
+ * the numbers are part of the interface: never renumber or reuse one, only
+ * append. the high nibble of the low byte groups them.
+ */
+typedef enum {
+    ERR_OK = 0x00,
+
+    /* console and command arguments */
+    ERR_UNKNOWN_COMMAND = 0x01,
+    ERR_BAD_ARGUMENT = 0x02,
+
+    /* external flash */
+    ERR_FLASH_ERASE = 0x10,
+    ERR_FLASH_WRITE = 0x11,
+    ERR_FLASH_READ = 0x12,
+    ERR_FLASH_MISMATCH = 0x13,
+    ERR_FLASH_NO_CHIP = 0x14,
+    ERR_FLASH_CALIBRATION = 0x15,
+    ERR_FLASH_NOT_BLANK = 0x16,
+
+    /* addresses and partitions */
+    ERR_OUT_OF_RANGE = 0x20,
+    ERR_UNALIGNED = 0x21,
+
+    /* application vector table checks */
+    ERR_BOOT_STACK_OUT_OF_RAM = 0x30,
+    ERR_BOOT_STACK_UNALIGNED = 0x31,
+    ERR_BOOT_RESET_OUT_OF_SLOT = 0x32,
+    ERR_BOOT_RESET_NOT_THUMB = 0x33,
+    ERR_BOOT_RESET_OUT_OF_IMAGE = 0x34,
+
+    /* intel hex / s-record uploads */
+    ERR_HEX_SYNTAX = 0x40,
+    ERR_HEX_CHECKSUM = 0x41,
+    ERR_HEX_RECORD = 0x42,
+
+    /* sparse image manifest */
+    ERR_MANIFEST_INVALID = 0x50,
+    ERR_MANIFEST_HASH = 0x51,
+    ERR_MANIFEST_FULL = 0x52,
+
+    /* the bootloader itself */
+    ERR_SELF_CHECK_CRC = 0x60,
+    ERR_PANIC = 0x61,
+    ERR_FAULT = 0x62,
+
+    /* production end of line test */
+    ERR_EOL_FAILED = 0x70,
+
+    /* application image header */
+    ERR_IMAGE_MAGIC = 0x80,
+    ERR_IMAGE_HEADER_CRC = 0x81,
+    ERR_IMAGE_LENGTH = 0x82,
+    ERR_IMAGE_ENTRY = 0x83,
+    ERR_IMAGE_BOARD = 0x84,
+    ERR_IMAGE_FLASH_SIZE = 0x85,
+    ERR_IMAGE_EXPIRED = 0x86,
+    ERR_IMAGE_TLV = 0x87,
+} Error_T;
+
+const char *error_str(int error);
+
+#endif
@@ -0,0 +1,63 @@
+#include "retry.h"
+#include "stm32h7xx_hal.h"
+#include "rng.h"
+
+extern RNG_HandleTypeDef rng;
+
+const Retry_Policy_T retry_flash = {
+    RETRY_FLASH_ATTEMPTS,
+    RETRY_FLASH_BACKOFF_MS,
+    RETRY_FLASH_BACKOFF_MAX_MS,
+    retry_flash_transient,
+};
+
+/* retries since boot, a growing count points at marginal hardware */
+static uint32_t retries;
+
+/*
+ * a failed transfer or a readback that doesn't match may well pass the next
+ * time. bad arguments, a missing chip or a range that isn't blank won't
+ */
+bool retry_flash_transient(Error_T error)
+{
+    switch (error) {
+    case ERR_FLASH_ERASE:
+    case ERR_FLASH_WRITE:
+    case ERR_FLASH_READ:
+    case ERR_FLASH_MISMATCH:
+        return true;
+    default:
+        return false;
+    }
+}
+
+/**
+ * @brief   run op until it succeeds, fails with a permanent error or runs out of attempts
+ * @param   policy  attempts, backoff and which errors are transient
+ * @param   op      the operation, called with context
+ * @retval  ERR_OK or the error of the last attempt
+ * @note    the backoff uses HAL_Delay(), so not for code running with interrupts masked.
+ *          up to as much again is added at random, so the retries don't keep
+ *          lining up with a periodic disturbance
+ */
+Error_T retry(const Retry_Policy_T *policy, Retry_Op_T op, void *context)
+{
+    uint32_t backoff = policy->backoff_ms;
+    Error_T error = op(context);
+
+    for (uint32_t attempt = 1; attempt < policy->attempts && error != ERR_OK && policy->transient(error); attempt++) {
+        HAL_Delay(backoff + rng_jitter(&rng, backoff));
+        if (backoff < policy->backoff_max_ms)
+            backoff = backoff * 2 < policy->backoff_max_ms ? backoff * 2 : policy->backoff_max_ms;
+
+        retries++;
+        error = op(context);
+    }
+
+    return error;
+}
+
+uint32_t retry_count(void)
+{
+    return retries;
+}
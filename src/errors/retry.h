@@ -0,0 +1,36 @@
+#ifndef RETRY_H_
+#define RETRY_H_
+
+#include <stdint.h>
+#include "errors.h"
+
+/*
+ * flash operations get this many tries in total by default. marginal boards
+ * glitch on a status read or a verify now and then, a retry saves the whole
+ * install or upload from being aborted
+ */
+#ifndef RETRY_FLASH_ATTEMPTS
+#define RETRY_FLASH_ATTEMPTS        3
+#endif
+#ifndef RETRY_FLASH_BACKOFF_MS
+#define RETRY_FLASH_BACKOFF_MS      2
+#endif
+#define RETRY_FLASH_BACKOFF_MAX_MS  50
+
+typedef struct {
+    uint32_t attempts;                  //tries in total, 1 never retries
+    uint32_t backoff_ms;                //pause before the first retry, doubles for each one after
+    uint32_t backoff_max_ms;
+    bool (*transient)(Error_T error);   //errors worth another try, the rest is reported at once
+} Retry_Policy_T;
+
+/* one attempt of the operation, it has to be safe to run again after a failure */
+typedef Error_T (*Retry_Op_T)(void *context);
+
+extern const Retry_Policy_T retry_flash;
+
+bool retry_flash_transient(Error_T error);
+Error_T retry(const Retry_Policy_T *policy, Retry_Op_T op, void *context);
+uint32_t retry_count(void);
+
+#endif
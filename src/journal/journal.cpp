@@ -0,0 +1,114 @@
+#include "journal.h"
+
+typedef struct {
+    uint32_t magic;
+    uint32_t head;
+    uint32_t seq;
+    Journal_Entry_T entries[JOURNAL_ENTRIES];
+} Journal_T;
+
+static Journal_T journal __attribute__((section(".noinit")));
+
+static bool journal_valid(void)
+{
+    if (journal.magic != JOURNAL_MAGIC || journal.head >= JOURNAL_ENTRIES)
+        return false;
+
+    for (uint32_t i = 0; i < JOURNAL_ENTRIES; i++) {
+        if (journal.entries[i].op > JOURNAL_OP_POWER_CYCLE || journal.entries[i].status > JOURNAL_STATUS_FAILED)
+            return false;
+    }
+
+    return true;
+}
+
+/**
+ * @brief   keep the entries of the previous run if they look sane, otherwise
+ *          (power on, ram garbage) start over, then log the boot itself
+ * @param   reset_flags RCC_RSR as read before the flags get cleared
+ */
+void journal_init(uint32_t reset_flags)
+{
+    Journal_Entry_T empty = {0, 0, 0, 0, 0, 0};
+
+    if (!journal_valid()) {
+        journal.magic = JOURNAL_MAGIC;
+        journal.head = 0;
+        journal.seq = 0;
+
+        for (uint32_t i = 0; i < JOURNAL_ENTRIES; i++) {
+            journal.entries[i] = empty;
+        }
+    }
+
+    journal_end(journal_begin(JOURNAL_OP_BOOT, reset_flags, 0), true);
+}
+
+/* called from the flash driver, so it has to stay out of the flash as well */
+uint32_t journal_begin(Journal_Op_T op, uint32_t address, uint32_t len)
+{
+    uint32_t slot = journal.head;
+    Journal_Entry_T *entry = &journal.entries[slot];
+
+    entry->seq = ++journal.seq;
+    entry->op = op;
+    entry->status = JOURNAL_STATUS_STARTED;
+    entry->address = address;
+    entry->len = len;
+
+    journal.head = (slot + 1) % JOURNAL_ENTRIES;
+    return slot;
+}
+
+void journal_end(uint32_t slot, bool ok)
+{
+    if (slot >= JOURNAL_ENTRIES)
+        return;
+
+    journal.entries[slot].status = ok ? JOURNAL_STATUS_OK : JOURNAL_STATUS_FAILED;
+}
+
+uint32_t journal_count(void)
+{
+    return journal.seq < JOURNAL_ENTRIES ? journal.seq : JOURNAL_ENTRIES;
+}
+
+/* age 0 is the newest entry */
+bool journal_entry(uint32_t age, Journal_Entry_T *entry)
+{
+    if (age >= journal_count())
+        return false;
+
+    *entry = journal.entries[(journal.head + JOURNAL_ENTRIES - 1 - age) % JOURNAL_ENTRIES];
+    return true;
+}
+
+const char *journal_op_str(uint8_t op)
+{
+    switch (op) {
+    case JOURNAL_OP_BOOT:
+        return "boot";
+    case JOURNAL_OP_ERASE:
+        return "erase";
+    case JOURNAL_OP_WRITE:
+        return "write";
+    case JOURNAL_OP_POWER_CYCLE:
+        return "power cycle";
+    default:
+        return "unknown";
+    }
+}
+
+const char *journal_status_str(uint8_t status)
+{
+    switch (status) {
+    case JOURNAL_STATUS_STARTED:
+        return "started";
+    case JOURNAL_STATUS_OK:
+        return "ok";
+    case JOURNAL_STATUS_FAILED:
+        return "failed";
+    default:
+        return "unknown";
+    }
+}
@@ -0,0 +1,45 @@
+#ifndef JOURNAL_H_
+#define JOURNAL_H_
+
+#include <stdint.h>
+
+/*
+ * ring of the last flash operations, kept in uninitialized shared ram so it
+ * survives a soft reset. an entry is written before the operation starts and
+ * completed after it, so an operation that never finished shows up as started.
+ */
+
+#define JOURNAL_ENTRIES 16
+#define JOURNAL_MAGIC   0x4A524E4C
+
+typedef enum {
+    JOURNAL_OP_BOOT = 1,    //address holds the RCC_RSR reset flags
+    JOURNAL_OP_ERASE,
+    JOURNAL_OP_WRITE,
+    JOURNAL_OP_POWER_CYCLE,
+} Journal_Op_T;
+
+typedef enum {
+    JOURNAL_STATUS_STARTED = 0,
+    JOURNAL_STATUS_OK,
+    JOURNAL_STATUS_FAILED,
+} Journal_Status_T;
+
+typedef struct {
+    uint32_t seq;
+    uint8_t op;
+    uint8_t status;
+    uint16_t reserved;
+    uint32_t address;
+    uint32_t len;
+} Journal_Entry_T;
+
+void journal_init(uint32_t reset_flags);
+uint32_t journal_begin(Journal_Op_T op, uint32_t address, uint32_t len);
+void journal_end(uint32_t slot, bool ok);
+uint32_t journal_count(void);
+bool journal_entry(uint32_t age, Journal_Entry_T *entry);
+const char *journal_op_str(uint8_t op);
+const char *journal_status_str(uint8_t status);
+
+#endif
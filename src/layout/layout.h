@@ -0,0 +1,56 @@
+#ifndef LAYOUT_H_
+#define LAYOUT_H_
+
+#include <stdint.h>
+#include "layout_map.h"
+
+/*
+ * a flash region described by its offset from the start of the external flash.
+ * the checks are done by the compiler as soon as a region type is used, so a
+ * layout that doesn't fit the chip or breaks sector alignment never builds.
+ */
+template <uint32_t BASE, uint32_t LEN>
+struct Region_T
+{
+    static constexpr uint32_t base = BASE;
+    static constexpr uint32_t len = LEN;
+    static constexpr uint32_t end = BASE + LEN;
+
+    static_assert(LEN != 0, "region is empty");
+    static_assert(BASE % LAYOUT_SECTOR_SIZE == 0, "region base is not sector aligned");
+    static_assert(LEN % LAYOUT_SECTOR_SIZE == 0, "region length is not a multiple of the sector size");
+    static_assert(BASE < LAYOUT_MEMORY_SIZE && LEN <= LAYOUT_MEMORY_SIZE - BASE,
+                  "region doesn't fit into the flash");
+
+    static constexpr bool contains(uint32_t address, uint32_t N)
+    {
+        return address >= base && address <= end && N <= end - address;
+    }
+
+    static constexpr bool overlaps(uint32_t address, uint32_t N)
+    {
+        return N != 0 && address < end && address + N > base;
+    }
+};
+
+template <typename A, typename B>
+constexpr bool regions_overlap(void)
+{
+    return A::base < B::end && B::base < A::end;
+}
+
+/* the application is executed in place, so its slot must start at 0x90000000 */
+typedef Region_T<LAYOUT_PRIMARY_BASE, LAYOUT_PRIMARY_LEN> Primary_Slot_T;
+typedef Region_T<LAYOUT_SECONDARY_BASE, LAYOUT_SECONDARY_LEN> Secondary_Slot_T;
+typedef Region_T<LAYOUT_METADATA_BASE, LAYOUT_METADATA_LEN> Metadata_T;
+typedef Region_T<LAYOUT_SCRATCH_BASE, LAYOUT_SCRATCH_LEN> Scratch_T;
+
+static_assert(Primary_Slot_T::base == 0, "primary slot must be mapped at the start of the window");
+static_assert(!regions_overlap<Primary_Slot_T, Secondary_Slot_T>(), "primary and secondary slots overlap");
+static_assert(!regions_overlap<Primary_Slot_T, Metadata_T>(), "primary slot and metadata overlap");
+static_assert(!regions_overlap<Secondary_Slot_T, Metadata_T>(), "secondary slot and metadata overlap");
+static_assert(!regions_overlap<Primary_Slot_T, Scratch_T>(), "primary slot and scratch overlap");
+static_assert(!regions_overlap<Secondary_Slot_T, Scratch_T>(), "secondary slot and scratch overlap");
+static_assert(!regions_overlap<Metadata_T, Scratch_T>(), "metadata and scratch overlap");
+
+#endif
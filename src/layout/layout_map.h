@@ -0,0 +1,41 @@
+#ifndef LAYOUT_MAP_H_
+#define LAYOUT_MAP_H_
+
+/*
+ * the memory map shared by the bootloader and the applications it boots.
+ * this file is also run through the c preprocessor to produce layout.ld, so
+ * only plain numbers are allowed here - no casts, suffixes or expressions
+ * that the linker can't evaluate.
+ */
+
+/* internal flash, holds the bootloader only */
+#define LAYOUT_BOOTLOADER_BASE  0x08000000
+#define LAYOUT_BOOTLOADER_LEN   0x20000
+
+/* bootloader api function table, right after the vector table */
+#define LAYOUT_API_TABLE        0x08000400
+
+/*
+ * state of the bootloader api, kept at the end of the d3 sram. applications
+ * calling the api must not use this range.
+ */
+#define LAYOUT_SHARED_RAM_BASE  0x3800F000
+#define LAYOUT_SHARED_RAM_LEN   0x1000
+
+/* external w25q64 flash, 8 Mbytes with 4 Kbytes erase sectors */
+#define LAYOUT_XIP_BASE         0x90000000
+#define LAYOUT_MEMORY_SIZE      0x800000
+#define LAYOUT_SECTOR_SIZE      0x1000
+
+/* partitions, as offsets from the start of the external flash */
+#define LAYOUT_PRIMARY_BASE     0x000000
+#define LAYOUT_PRIMARY_LEN      0x300000
+#define LAYOUT_SECONDARY_BASE   0x300000
+#define LAYOUT_SECONDARY_LEN    0x300000
+#define LAYOUT_METADATA_BASE    0x600000
+#define LAYOUT_METADATA_LEN     0x010000
+/* last block, free for diagnostics to erase and program at will */
+#define LAYOUT_SCRATCH_BASE     0x7F0000
+#define LAYOUT_SCRATCH_LEN      0x010000
+
+#endif
@@ -0,0 +1,26 @@
+#ifndef LE_H_
+#define LE_H_
+
+#include <stdint.h>
+
+/*
+ * the words of the metadata and image headers are little endian in the
+ * flash, whatever reads or writes them. structures are decoded field by field
+ * with these instead of casting the flash bytes, which says nothing about
+ * padding or alignment.
+ */
+
+static inline uint32_t le32_get(const uint8_t *p)
+{
+    return (uint32_t)p[0] | (uint32_t)p[1] << 8 | (uint32_t)p[2] << 16 | (uint32_t)p[3] << 24;
+}
+
+static inline void le32_put(uint8_t *p, uint32_t value)
+{
+    p[0] = value;
+    p[1] = value >> 8;
+    p[2] = value >> 16;
+    p[3] = value >> 24;
+}
+
+#endif
@@ -0,0 +1,171 @@
+#include "hexfile.h"
+#include <string.h>
+
+static int hex_nibble(char c)
+{
+    if (c >= '0' && c <= '9')
+        return c - '0';
+    if (c >= 'A' && c <= 'F')
+        return c - 'A' + 10;
+    if (c >= 'a' && c <= 'f')
+        return c - 'a' + 10;
+    return -1;
+}
+
+/* decodes the hex digits after the start code, false on an odd count or a non hex digit */
+static bool hex_bytes(const char * text, uint8_t * bytes, uint32_t * count)
+{
+    uint32_t len = strlen(text);
+
+    //trailing whitespace from the terminal
+    while (len > 0 && (text[len - 1] == ' ' || text[len - 1] == '\t'))
+        len--;
+
+    if (len == 0 || len % 2 || len / 2 > HEX_RECORD_MAX + 5)
+        return false;
+
+    for (uint32_t i = 0; i < len / 2; i++) {
+        int high = hex_nibble(text[2 * i]);
+        int low = hex_nibble(text[2 * i + 1]);
+
+        if (high < 0 || low < 0)
+            return false;
+        bytes[i] = (high << 4) | low;
+    }
+
+    *count = len / 2;
+    return true;
+}
+
+Hex_Parser_T::Hex_Parser_T(void)
+{
+    m_base = 0;
+}
+
+/* a new file starts without extended address */
+void Hex_Parser_T::reset(void)
+{
+    m_base = 0;
+}
+
+/**
+ * @brief   parse one line
+ * @param   line    text without the line ending
+ * @param   record  filled with the decoded record
+ * @retval  ERR_OK, ERR_HEX_SYNTAX, ERR_HEX_CHECKSUM or ERR_HEX_RECORD
+ */
+Error_T Hex_Parser_T::parse(const char * line, Hex_Record_T * record)
+{
+    while (*line == ' ')
+        line++;
+
+    record->type = HEX_RECORD_NONE;
+    record->address = 0;
+    record->len = 0;
+
+    if (*line == ':')
+        return m_ihex(line + 1, record);
+    if (*line == 'S' || *line == 's')
+        return m_srec(line + 1, record);
+
+    return ERR_HEX_SYNTAX;
+}
+
+/* :LLAAAATT<data>CC, the checksum makes all bytes add up to zero */
+Error_T Hex_Parser_T::m_ihex(const char * line, Hex_Record_T * record)
+{
+    uint8_t bytes[HEX_RECORD_MAX + 5];
+    uint32_t count = 0;
+    uint8_t sum = 0;
+
+    if (!hex_bytes(line, bytes, &count) || count < 5 || count != bytes[0] + 5u)
+        return ERR_HEX_SYNTAX;
+
+    for (uint32_t i = 0; i < count; i++) {
+        sum += bytes[i];
+    }
+    if (sum != 0)
+        return ERR_HEX_CHECKSUM;
+
+    uint32_t len = bytes[0];
+    uint32_t offset = (bytes[1] << 8) | bytes[2];
+    const uint8_t * data = &bytes[4];
+
+    switch (bytes[3]) {
+    case 0x00:
+        record->type = HEX_RECORD_DATA;
+        record->address = m_base + offset;
+        record->len = len;
+        memcpy(record->data, data, len);
+        return ERR_OK;
+    case 0x01:
+        record->type = HEX_RECORD_END;
+        return ERR_OK;
+    case 0x02:
+        if (len != 2)
+            return ERR_HEX_RECORD;
+        m_base = ((data[0] << 8) | data[1]) << 4;
+        return ERR_OK;
+    case 0x04:
+        if (len != 2)
+            return ERR_HEX_RECORD;
+        m_base = ((data[0] << 8) | data[1]) << 16;
+        return ERR_OK;
+    case 0x03:
+    case 0x05:
+        //start address, the entry comes from the vector table anyway
+        return ERR_OK;
+    default:
+        return ERR_HEX_RECORD;
+    }
+}
+
+/* S<type><count><address><data><checksum>, the checksum is the ones complement of the sum */
+Error_T Hex_Parser_T::m_srec(const char * line, Hex_Record_T * record)
+{
+    uint8_t bytes[HEX_RECORD_MAX + 5];
+    uint32_t count = 0;
+    uint32_t address_len;
+    uint8_t sum = 0;
+    char type = *line;
+
+    switch (type) {
+    case '0': case '1': case '5': case '9':
+        address_len = 2; break;
+    case '2': case '6': case '8':
+        address_len = 3; break;
+    case '3': case '7':
+        address_len = 4; break;
+    default:
+        return ERR_HEX_RECORD;
+    }
+
+    if (!hex_bytes(line + 1, bytes, &count) || count < address_len + 2 || count != bytes[0] + 1u)
+        return ERR_HEX_SYNTAX;
+
+    for (uint32_t i = 0; i < count; i++) {
+        sum += bytes[i];
+    }
+    if (sum != 0xFF)
+        return ERR_HEX_CHECKSUM;
+
+    uint32_t address = 0;
+    for (uint32_t i = 0; i < address_len; i++) {
+        address = (address << 8) | bytes[1 + i];
+    }
+
+    switch (type) {
+    case '1': case '2': case '3':
+        record->type = HEX_RECORD_DATA;
+        record->address = address;
+        record->len = count - address_len - 2;
+        memcpy(record->data, &bytes[1 + address_len], record->len);
+        return ERR_OK;
+    case '7': case '8': case '9':
+        record->type = HEX_RECORD_END;
+        return ERR_OK;
+    default:
+        //header and record counts
+        return ERR_OK;
+    }
+}
@@ -0,0 +1,36 @@
+#ifndef HEXFILE_H_
+#define HEXFILE_H_
+
+#include <stdint.h>
+#include "errors.h"
+
+/* S3 and type 00 records carry at most 255 bytes, the console line limits it further */
+#define HEX_RECORD_MAX 255
+
+typedef enum {
+    HEX_RECORD_NONE = 0,    //valid, but nothing to program (header, address, count)
+    HEX_RECORD_DATA,
+    HEX_RECORD_END,
+} Hex_Record_Type_T;
+
+typedef struct {
+    Hex_Record_Type_T type;
+    uint32_t address;       //absolute, extended addresses already applied
+    uint32_t len;
+    uint8_t data[HEX_RECORD_MAX];
+} Hex_Record_T;
+
+/* parses intel hex and motorola s-record lines, picked by the first character */
+class Hex_Parser_T
+{
+private:
+    uint32_t m_base;
+    Error_T m_ihex(const char * line, Hex_Record_T * record);
+    Error_T m_srec(const char * line, Hex_Record_T * record);
+public:
+    Hex_Parser_T(void);
+    void reset(void);
+    Error_T parse(const char * line, Hex_Record_T * record);
+};
+
+#endif
@@ -0,0 +1,140 @@
+#include "loader.h"
+#include "stm32h7xx_hal.h"
+#include "w25q.h"
+#include "retry.h"
+#include <string.h>
+
+extern Flash_T flash;
+
+Loader_T::Loader_T(void)
+{
+    begin();
+}
+
+void Loader_T::begin(void)
+{
+    memset(m_erased, 0, sizeof(m_erased));
+    memset(&m_manifest, 0, sizeof(m_manifest));
+    m_bytes = 0;
+    m_records = 0;
+}
+
+Error_T Loader_T::m_erase(uint32_t offset, uint32_t N)
+{
+    uint32_t first = (offset - Primary_Slot_T::base) / LAYOUT_SECTOR_SIZE;
+    uint32_t last = (offset + N - 1 - Primary_Slot_T::base) / LAYOUT_SECTOR_SIZE;
+
+    for (uint32_t sector = first; sector <= last; sector++) {
+        if (m_erased[sector / 8] & (1 << (sector % 8)))
+            continue;
+
+        uint32_t address = Primary_Slot_T::base + sector * LAYOUT_SECTOR_SIZE;
+        if (!flash.sector_erase(address, address))
+            return ERR_FLASH_ERASE;
+
+        m_erased[sector / 8] |= 1 << (sector % 8);
+    }
+
+    return ERR_OK;
+}
+
+/*
+ * m_erase() skips sectors it has erased before, so another try only programs
+ * the record again. that fixes a transfer that failed or a readback that
+ * didn't come through, not bits that read back wrong
+ */
+static bool loader_transient(Error_T error)
+{
+    return error == ERR_FLASH_WRITE || error == ERR_FLASH_READ;
+}
+
+static const Retry_Policy_T loader_retry = {
+    RETRY_FLASH_ATTEMPTS,
+    RETRY_FLASH_BACKOFF_MS,
+    RETRY_FLASH_BACKOFF_MAX_MS,
+    loader_transient,
+};
+
+struct Loader_Attempt_T {
+    Loader_T * loader;
+    const Hex_Record_T * record;
+};
+
+/* programming the same data again only clears the bits that didn't make it */
+Error_T Loader_T::m_program(const Hex_Record_T * record)
+{
+    uint32_t offset = record->address - QSPI_BASE;
+
+    Error_T error = m_erase(offset, record->len);
+    if (error != ERR_OK)
+        return error;
+
+    if (!flash.write_N_bytes(record->len, offset, (uint8_t *)record->data))
+        return ERR_FLASH_WRITE;
+
+    if (!flash.read_N_bytes(record->len, offset, m_verify))
+        return ERR_FLASH_READ;
+
+    if (memcmp(m_verify, record->data, record->len) != 0)
+        return ERR_FLASH_MISMATCH;
+
+    return ERR_OK;
+}
+
+Error_T Loader_T::m_program_attempt(void * context)
+{
+    Loader_Attempt_T * attempt = (Loader_Attempt_T *)context;
+
+    return attempt->loader->m_program(attempt->record);
+}
+
+/**
+ * @brief   program one data record and read it back
+ * @param   record  data record, other record types are ignored
+ * @retval  ERR_OK or the reason the record couldn't be programmed
+ * @note    overlapping records read back wrong, the flash can't clear bits back to one.
+ *          failed transfers are retried, see loader_transient()
+ */
+Error_T Loader_T::write(const Hex_Record_T * record)
+{
+    if (record->type != HEX_RECORD_DATA || record->len == 0)
+        return ERR_OK;
+
+    if (record->address < QSPI_BASE || !Primary_Slot_T::contains(record->address - QSPI_BASE, record->len))
+        return ERR_OUT_OF_RANGE;
+
+    uint32_t offset = record->address - QSPI_BASE;
+
+    if (!manifest_add_range(&m_manifest, offset, record->len))
+        return ERR_MANIFEST_FULL;
+
+    Loader_Attempt_T attempt = {this, record};
+    Error_T error = retry(&loader_retry, m_program_attempt, &attempt);
+    if (error != ERR_OK)
+        return error;
+
+    m_bytes += record->len;
+    m_records++;
+    return ERR_OK;
+}
+
+/* after the end record, stores the manifest of what was programmed */
+Error_T Loader_T::finish(void)
+{
+    return manifest_write(&m_manifest);
+}
+
+const Manifest_T * Loader_T::manifest(void)
+{
+    return &m_manifest;
+}
+
+uint32_t Loader_T::bytes(void)
+{
+    return m_bytes;
+}
+
+uint32_t Loader_T::records(void)
+{
+    return m_records;
+}
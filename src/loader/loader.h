@@ -0,0 +1,39 @@
+#ifndef LOADER_H_
+#define LOADER_H_
+
+#include <stdint.h>
+#include "errors.h"
+#include "hexfile.h"
+#include "layout.h"
+#include "manifest.h"
+
+#define LOADER_SECTORS (Primary_Slot_T::len / LAYOUT_SECTOR_SIZE)
+
+/*
+ * programs decoded hex/srec records into the primary slot. the records carry
+ * the link addresses of the application, i.e. the 0x90000000 window.
+ * a sector is erased the first time a record touches it, sectors no record
+ * touches keep their contents. the covered ranges end up in the manifest.
+ */
+class Loader_T
+{
+private:
+    uint8_t m_erased[LOADER_SECTORS / 8];
+    uint32_t m_bytes;
+    uint32_t m_records;
+    uint8_t m_verify[HEX_RECORD_MAX];
+    Manifest_T m_manifest;
+    Error_T m_erase(uint32_t offset, uint32_t N);
+    Error_T m_program(const Hex_Record_T * record);
+    static Error_T m_program_attempt(void * context);
+public:
+    Loader_T(void);
+    void begin(void);
+    Error_T write(const Hex_Record_T * record);
+    Error_T finish(void);
+    const Manifest_T * manifest(void);
+    uint32_t bytes(void);
+    uint32_t records(void);
+};
+
+#endif
@@ -0,0 +1,143 @@
+#include "manifest.h"
+#include "layout.h"
+#include "crc32.h"
+#include "irq.h"
+#include "w25q.h"
+#include <stddef.h>
+#include <string.h>
+
+extern Flash_T flash;
+
+static uint8_t manifest_buffer[256];
+
+/* copy of the stored manifest, the flash is only read once per boot. dropped by bootloader api writes */
+__attribute__((section(".shared_ram"))) static Manifest_T manifest_cache;
+__attribute__((section(".shared_ram"))) static bool manifest_cached;
+
+static uint32_t manifest_crc(const Manifest_T * manifest)
+{
+    Crc32_T crc;
+
+    crc.update((const uint8_t *)manifest, offsetof(Manifest_T, crc));
+    return crc.finalize();
+}
+
+/**
+ * @brief   add the bytes at offset, merged into the last range if they follow it
+ * @retval  false if the manifest is out of ranges
+ */
+bool manifest_add_range(Manifest_T * manifest, uint32_t offset, uint32_t len)
+{
+    if (manifest->count > 0) {
+        Manifest_Range_T * last = &manifest->ranges[manifest->count - 1];
+
+        if (last->offset + last->len == offset) {
+            last->len += len;
+            return true;
+        }
+    }
+
+    if (manifest->count >= MANIFEST_RANGES)
+        return false;
+
+    manifest->ranges[manifest->count].offset = offset;
+    manifest->ranges[manifest->count].len = len;
+    manifest->count++;
+    return true;
+}
+
+/* sha-256 over the ranges in their order, read back from the flash */
+Error_T manifest_hash(const Manifest_T * manifest, uint8_t digest[SHA256_DIGEST_SIZE])
+{
+    Sha256_T sha;
+
+    for (uint32_t i = 0; i < manifest->count; i++) {
+        uint32_t offset = manifest->ranges[i].offset;
+        uint32_t left = manifest->ranges[i].len;
+
+        while (left > 0) {
+            uint32_t N = left < sizeof(manifest_buffer) ? left : sizeof(manifest_buffer);
+
+            if (!flash.read_N_bytes(N, offset, manifest_buffer))
+                return ERR_FLASH_READ;
+
+            sha.update(manifest_buffer, N);
+            offset += N;
+            left -= N;
+        }
+    }
+
+    sha.finalize(digest);
+    return ERR_OK;
+}
+
+/**
+ * @brief   seal the manifest with the hash of its ranges and store it
+ * @param   manifest    ranges filled in, the rest is set here
+ */
+Error_T manifest_write(Manifest_T * manifest)
+{
+    manifest->magic = MANIFEST_MAGIC;
+    manifest->version = MANIFEST_VERSION;
+
+    Error_T error = manifest_hash(manifest, manifest->sha256);
+    if (error != ERR_OK)
+        return error;
+
+    manifest->crc = manifest_crc(manifest);
+
+    manifest_invalidate();
+
+    uint32_t basepri = irq_commit_enter();
+
+    if (!flash.sector_erase(MANIFEST_OFFSET, MANIFEST_OFFSET))
+        error = ERR_FLASH_ERASE;
+    else if (!flash.write_N_bytes(sizeof(Manifest_T), MANIFEST_OFFSET, (uint8_t *)manifest))
+        error = ERR_FLASH_WRITE;
+
+    irq_commit_exit(basepri);
+    if (error != ERR_OK)
+        return error;
+
+    manifest_cache = *manifest;
+    manifest_cached = true;
+    return ERR_OK;
+}
+
+Error_T manifest_read(Manifest_T * manifest)
+{
+    if (!manifest_cached) {
+        if (!flash.read_N_bytes(sizeof(Manifest_T), MANIFEST_OFFSET, (uint8_t *)&manifest_cache))
+            return ERR_FLASH_READ;
+        manifest_cached = true;
+    }
+
+    *manifest = manifest_cache;
+
+    if (manifest->magic != MANIFEST_MAGIC || manifest->version != MANIFEST_VERSION ||
+        manifest->count > MANIFEST_RANGES || manifest->crc != manifest_crc(manifest))
+        return ERR_MANIFEST_INVALID;
+
+    return ERR_OK;
+}
+
+/* for writes to the metadata that don't go through manifest_write() */
+void manifest_invalidate(void)
+{
+    manifest_cached = false;
+}
+
+/* rehash the defined regions and compare against the stored hash */
+Error_T manifest_verify(const Manifest_T * manifest)
+{
+    uint8_t digest[SHA256_DIGEST_SIZE];
+
+    Error_T error = manifest_hash(manifest, digest);
+    if (error != ERR_OK)
+        return error;
+
+    if (memcmp(digest, manifest->sha256, sizeof(digest)) != 0)
+        return ERR_MANIFEST_HASH;
+
+    return ERR_OK;
+}
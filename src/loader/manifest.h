@@ -0,0 +1,54 @@
+#ifndef MANIFEST_H_
+#define MANIFEST_H_
+
+#include <stddef.h>
+#include <stdint.h>
+#include "errors.h"
+#include "layout_map.h"
+#include "sha256.h"
+
+#define MANIFEST_MAGIC      0x4D414E49
+#define MANIFEST_VERSION    1
+#define MANIFEST_RANGES     32
+
+/* first sector of the metadata partition */
+#define MANIFEST_OFFSET     LAYOUT_METADATA_BASE
+
+/* a defined part of a sparse image, offsets relative to the start of the flash */
+typedef struct {
+    uint32_t offset;
+    uint32_t len;
+} Manifest_Range_T;
+
+/*
+ * written after an upload. the gaps between the ranges hold whatever was
+ * there before, so only the ranges are hashed.
+ */
+typedef struct {
+    uint32_t magic;
+    uint32_t version;
+    uint32_t count;
+    Manifest_Range_T ranges[MANIFEST_RANGES];
+    uint8_t sha256[SHA256_DIGEST_SIZE];
+    uint32_t crc;   //crc32 of everything above
+} Manifest_T;
+
+/*
+ * too many fields to decode one by one, the manifest is stored as the
+ * structure is laid out. that is only the flash format as long as these hold
+ */
+static_assert(__BYTE_ORDER__ == __ORDER_LITTLE_ENDIAN__, "the manifest is stored in the cpu's byte order");
+static_assert(sizeof(Manifest_Range_T) == 8, "manifest range has padding");
+static_assert(offsetof(Manifest_T, ranges) == 12, "manifest layout changed");
+static_assert(offsetof(Manifest_T, sha256) == 12 + MANIFEST_RANGES * 8, "manifest layout changed");
+static_assert(offsetof(Manifest_T, crc) == 12 + MANIFEST_RANGES * 8 + SHA256_DIGEST_SIZE, "manifest layout changed");
+static_assert(sizeof(Manifest_T) == offsetof(Manifest_T, crc) + 4, "manifest has padding");
+
+bool manifest_add_range(Manifest_T * manifest, uint32_t offset, uint32_t len);
+Error_T manifest_hash(const Manifest_T * manifest, uint8_t digest[SHA256_DIGEST_SIZE]);
+Error_T manifest_write(Manifest_T * manifest);
+Error_T manifest_read(Manifest_T * manifest);
+void manifest_invalidate(void);
+Error_T manifest_verify(const Manifest_T * manifest);
+
+#endif
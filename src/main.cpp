@@ -1,22 +1,260 @@
 #include "bsp.h"
 #include "usart.h"
 #include "qspi.h"
+#include "adc.h"
+#include "rng.h"
+#include "layout.h"
+#include "w25q.h"
+#include "calibration.h"
+#include "boot.h"
+#include "journal.h"
+#include "profile.h"
+#include "slots.h"
+#include "boot_log.h"
+#include "console.h"
+#include "commands.h"
 #include "stm32h7xx_hal.h"
 
 static UART_HandleTypeDef serial;
-static QSPI_HandleTypeDef qspi;
+static Console_T console;
+
+/* also used by the bootloader api after the application has started */
+__attribute__((section(".shared_ram"))) QSPI_HandleTypeDef hqspi;
+__attribute__((section(".shared_ram"))) Flash_T flash;
+
+ADC_HandleTypeDef adc;
+RNG_HandleTypeDef rng;
+
+/* a swap takes a while, so the console says what it is about to do */
+static void print_update(void)
+{
+    Slot_State_T state;
+
+    if (slot_state_read(&state) != ERR_OK)
+        return;
+
+    if (state.pending != SLOT_NONE)
+        console.print("installing %lu bytes from slot %lu\r\n", state.size, state.pending);
+    else if (state.trial == SLOT_TRIAL_TESTING || state.trial == SLOT_TRIAL_REVERTING)
+        console.print("the installed image didn't confirm, reverting %lu bytes\r\n", state.size);
+}
+
+/*
+ * an update the application staged and marked before the last reset, or the
+ * revert of one that didn't confirm its trial
+ */
+static void report_update(const Boot_Decision_T *decision)
+{
+    Error_T error = decision->update;
+    Boot_Log_Event_T event = decision->pending != SLOT_NONE ? BOOT_LOG_INSTALL : BOOT_LOG_REVERT;
+
+    switch (decision->action) {
+    case SLOT_ACTION_NONE:
+        break;
+    case SLOT_ACTION_INSTALLED:
+        boot_log(BOOT_LOG_INSTALL, error, decision->size);
+        console.print("install done, the image starts on trial\r\n");
+        break;
+    case SLOT_ACTION_SAME:
+        boot_log(BOOT_LOG_INSTALL, error, 0);
+        console.print("the staged image is installed already, nothing erased\r\n");
+        break;
+    case SLOT_ACTION_REFUSED:
+        boot_log(BOOT_LOG_INSTALL, error, decision->size);
+        console.print("install refused: %s\r\n", error_str(error));
+        break;
+    case SLOT_ACTION_REVERTED:
+        boot_log(BOOT_LOG_REVERT, error, decision->size);
+        console.print("revert done, back to the previous image\r\n");
+        break;
+    case SLOT_ACTION_KEPT:
+        boot_log(BOOT_LOG_REVERT, error, 0);
+        console.print("no previous image to revert to (%s), keeping the installed one\r\n", error_str(error));
+        break;
+    case SLOT_ACTION_FAILED:
+        boot_log(event, error, decision->size);
+        console.print("slot update failed: %s, carrying on with it on the next boot\r\n", error_str(error));
+        break;
+    }
+}
+
+static const char *trial_str(uint32_t trial)
+{
+    switch (trial) {
+    case SLOT_TRIAL_NONE:
+        return "none";
+    case SLOT_TRIAL_INSTALLED:
+        return "installed";
+    case SLOT_TRIAL_TESTING:
+        return "testing";
+    case SLOT_TRIAL_REVERTING:
+        return "reverting";
+    default:
+        return "unknown";
+    }
+}
+
+static void print_image(const char *slot, Error_T error, uint32_t version)
+{
+    if (error == ERR_OK)
+        console.print("decision: %s slot version %lu\r\n", slot, version);
+    else
+        console.print("decision: %s slot %s\r\n", slot, error_str(error));
+}
+
+/* the trace of the decision, the console gets all of it and the log one event */
+static void report_decision(const Boot_Decision_T *decision)
+{
+    Error_T reason = ERR_OK;
+
+    if (decision->action == SLOT_ACTION_FAILED)
+        reason = decision->update;
+    else if (decision->primary != ERR_OK)
+        reason = decision->primary;
+
+    boot_log(BOOT_LOG_DECISION, reason,
+             (decision->action & 0xFF) | (decision->trial & 0xFF) << 8 | (decision->pending & 0xFF) << 16 |
+                 (uint32_t)decision->boot << 24);
+
+    if (decision->state == ERR_OK) {
+        if (decision->pending != SLOT_NONE)
+            console.print("decision: install of slot %lu asked for, ", decision->pending);
+        else
+            console.print("decision: no install asked for, ");
+        console.print("trial %s\r\n", trial_str(decision->trial));
+    } else {
+        console.print("decision: slot state %s\r\n", error_str(decision->state));
+    }
+
+    print_image("primary", decision->primary, decision->primary_version);
+    print_image("secondary", decision->secondary, decision->secondary_version);
+
+    if (decision->boot)
+        console.print("decision: start the application\r\n");
+    else
+        console.print("decision: stay in the bootloader, %s\r\n", error_str(reason));
+}
+
+static void log_calibration(const Qspi_Calibration_T *cal)
+{
+    boot_log(BOOT_LOG_CALIBRATION, cal->ok ? ERR_OK : ERR_FLASH_CALIBRATION, qspi_clock_hz(&hqspi) / 1000000);
+}
+
+static void print_calibration(const Qspi_Calibration_T *cal)
+{
+    if (cal->temperature_valid)
+        console.print("qspi: die at %ld C%s\r\n", cal->temperature, cal->derated ? ", derated" : "");
+
+    if (cal->ok) {
+        console.print("qspi: %lu MHz, %u dummy cycles, %s sample shift\r\n", qspi_clock_hz(&hqspi) / 1000000,
+                      cal->dummy_cycles, cal->sample_shift == QSPI_SAMPLE_SHIFTING_NONE ? "no" : "half cycle");
+    } else {
+        console.print("qspi: calibration failed, staying at %lu MHz\r\n", qspi_clock_hz(&hqspi) / 1000000);
+    }
+}
 
 int main(void)
 {
+    profile_init();
+
     bsp_init();
+    profile_mark("bsp");
+
+    uint32_t reset_flags = RCC->RSR;
+    __HAL_RCC_CLEAR_RESET_FLAGS();
+
+    journal_init(reset_flags);
+    boot_log_init();
+    boot_log(BOOT_LOG_BOOT, ERR_OK, reset_flags);
+
+    /* a corrupted bootloader keeps to the safe clock and leaves the flash contents alone */
+    uint32_t self_crc;
+    Error_T self_check = boot_self_check(&self_crc);
+    boot_log(BOOT_LOG_SELF_CHECK, self_check, self_crc);
+    bool degraded = self_check != ERR_OK;
 
     usart_init(&serial, USART1);
+    adc_init(&adc);
+    bool rng_ok = rng_init(&rng);
+    profile_mark("peripherals");
+
+    qspi_init(&hqspi);
+    flash.init();
+    profile_mark("flash");
 
-    qspi_init(&qspi);
+    Qspi_Calibration_T cal = degraded ? qspi_calibration_safe() : qspi_calibrate();
+    log_calibration(&cal);
+    profile_mark("calibration");
+
+    console.init(&serial, commands, commands_count);
+    profile_mark("console");
+
+    Boot_Decision_T decision;
+
+    if (degraded)
+        console.print("bootloader image crc mismatch (0x%08lx), running in safe mode\r\n", self_crc);
+    else
+        print_update();
+
+    boot_decide(&decision, !degraded);
+    report_update(&decision);
+    profile_mark("install");
+
+    print_calibration(&cal);
+    if (!rng_ok)
+        console.print("rng: failed its health test, flash retries back off without jitter\r\n");
+    journal_print(console);
+    profile_print(console);
+    report_decision(&decision);
+
+    /* with nothing to start the bootloader is the safe mode, its console takes loads and installs */
+    bool autoboot = decision.boot;
+    uint32_t led_ms = autoboot ? BOOT_LED_MS : BOOT_LED_SAFE_MS;
+
+    uint32_t led_tick = HAL_GetTick();
+    uint32_t temp_tick = led_tick;
+    uint32_t boot_tick = led_tick;
+
+    if (autoboot)
+        console.print("starting the application in %lu ms, send anything to stay\r\n", (uint32_t)BOOT_DELAY_MS);
+    else if (decision.action == SLOT_ACTION_FAILED)
+        console.print("the primary slot is half swapped, staying in the bootloader\r\n");
+    else
+        console.print("no valid application, staying in the bootloader until one is loaded\r\n");
 
     while (1) {
-        HAL_GPIO_TogglePin(GPIOE, GPIO_PIN_3);
-        HAL_Delay(500);
+        console.poll();
+
+        if (console.idle()) {
+            console.print("\r\nno input for %lu s, closing the session\r\n", (uint32_t)CONSOLE_IDLE_MS / 1000);
+            console.close_session();
+            boot_log(BOOT_LOG_SESSION_IDLE, ERR_OK, CONSOLE_IDLE_MS);
+        }
+
+        /* a host that went quiet leaves the decision to the boot delay again, long gone by then */
+        if (autoboot && !console.session() && HAL_GetTick() - boot_tick >= BOOT_DELAY_MS) {
+            console.print("starting the application\r\n");
+            Error_T error = boot_application();
+            console.print("can't start the application: %s (0x%02x)\r\n", error_str(error), error);
+            autoboot = false;
+            led_ms = BOOT_LED_SAFE_MS;
+        }
+
+        if (HAL_GetTick() - led_tick >= led_ms) {
+            led_tick += led_ms;
+            HAL_GPIO_TogglePin(GPIOE, GPIO_PIN_3);
+        }
+
+        if (!degraded && HAL_GetTick() - temp_tick >= QSPI_TEMP_CHECK_MS) {
+            temp_tick += QSPI_TEMP_CHECK_MS;
+
+            if (qspi_temperature_shifted(&cal)) {
+                cal = qspi_calibrate();
+                boot_log(BOOT_LOG_TEMPERATURE, ERR_OK, cal.temperature);
+                log_calibration(&cal);
+                print_calibration(&cal);
+            }
+        }
     }
 }
 
@@ -29,6 +267,6 @@ extern "C" {
 
     void USART1_IRQHandler(void)
     {
-        HAL_UART_IRQHandler(&serial);
+        console.irq_handler();
     }
 }
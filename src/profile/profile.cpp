@@ -0,0 +1,58 @@
+#include "profile.h"
+#include "stm32h7xx_hal.h"
+
+static Profile_Mark_T profile_marks[PROFILE_MARKS];
+static uint32_t profile_marks_count;
+
+static uint32_t profile_last_cycles;
+static uint32_t profile_last_hz;
+static uint64_t profile_us;
+
+/* safe to call before the clocks are set up, the core runs on the hsi then */
+void profile_init(void)
+{
+    CoreDebug->DEMCR |= CoreDebug_DEMCR_TRCENA_Msk;
+    DWT->LAR = 0xC5ACCE55;
+    DWT->CYCCNT = 0;
+    DWT->CTRL |= DWT_CTRL_CYCCNTENA_Msk;
+
+    profile_marks_count = 0;
+    profile_last_cycles = 0;
+    profile_last_hz = SystemCoreClock;
+    profile_us = 0;
+}
+
+/**
+ * @brief   timestamp the end of a boot phase
+ * @param   name    static string, only the pointer is kept
+ * @note    the cycles of a phase are converted at the clock it started with, so
+ *          the phase that switches to the pll is only roughly right
+ */
+void profile_mark(const char *name)
+{
+    uint32_t cycles = DWT->CYCCNT;
+
+    profile_us += (uint64_t)(cycles - profile_last_cycles) * 1000000 / profile_last_hz;
+    profile_last_cycles = cycles;
+    profile_last_hz = SystemCoreClock;
+
+    if (profile_marks_count >= PROFILE_MARKS)
+        return;
+
+    profile_marks[profile_marks_count].name = name;
+    profile_marks[profile_marks_count].us = profile_us;
+    profile_marks_count++;
+}
+
+uint32_t profile_count(void)
+{
+    return profile_marks_count;
+}
+
+const Profile_Mark_T *profile_get(uint32_t index)
+{
+    if (index >= profile_marks_count)
+        return 0;
+
+    return &profile_marks[index];
+}
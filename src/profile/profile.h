@@ -0,0 +1,23 @@
+#ifndef PROFILE_H_
+#define PROFILE_H_
+
+#include <stdint.h>
+
+/*
+ * boot phase timestamps off the dwt cycle counter. a mark closes the phase
+ * started by the previous one, so the mark names the work done before it.
+ */
+
+#define PROFILE_MARKS 16
+
+typedef struct {
+    const char *name;
+    uint32_t us;    //since profile_init()
+} Profile_Mark_T;
+
+void profile_init(void);
+void profile_mark(const char *name);
+uint32_t profile_count(void);
+const Profile_Mark_T *profile_get(uint32_t index);
+
+#endif
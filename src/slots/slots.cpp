@@ -0,0 +1,561 @@
+#include "slots.h"
+#include "layout.h"
+#include "crc32.h"
+#include "irq.h"
+#include "retry.h"
+#include "w25q.h"
+#include "le.h"
+#include "image_header.h"
+#include "manifest.h"
+#include <stddef.h>
+#include <string.h>
+
+extern Flash_T flash;
+
+static_assert(Primary_Slot_T::len == Secondary_Slot_T::len, "a swap needs slots of the same size");
+static_assert(SLOT_STATE_OFFSET + SLOT_STATE_COPIES * LAYOUT_SECTOR_SIZE <= SLOT_SWAP_OFFSET,
+              "slot state copies overlap the swap progress");
+static_assert(Metadata_T::contains(SLOT_SWAP_BUFFER, LAYOUT_SECTOR_SIZE), "swap buffer outside the metadata");
+static_assert(SLOT_SWAP_SIZE + Primary_Slot_T::len / LAYOUT_SECTOR_SIZE * SLOT_SWAP_STEPS <= LAYOUT_SECTOR_SIZE,
+              "swap progress doesn't fit its sector");
+
+static uint8_t slot_buffer[LAYOUT_SECTOR_SIZE];
+static uint8_t slot_verify[LAYOUT_SECTOR_SIZE];
+
+struct Slot_Copy_T {
+    uint32_t from;
+    uint32_t to;
+};
+
+/*
+ * the state as read from the flash, so the install decision doesn't depend
+ * on the flash answering every time it is asked, and the copy it came from.
+ * the bootloader api updates it after the application took over the .bss
+ */
+__attribute__((section(".shared_ram"))) static Slot_State_T slot_state_cache;
+__attribute__((section(".shared_ram"))) static uint32_t slot_state_copy;
+__attribute__((section(".shared_ram"))) static bool slot_state_cached;
+
+static void slot_state_encode(uint8_t *raw, const Slot_State_T *state)
+{
+    le32_put(raw, state->magic);
+    le32_put(raw + 4, state->sequence);
+    le32_put(raw + 8, state->pending);
+    le32_put(raw + 12, state->size);
+    le32_put(raw + 16, state->trial);
+    le32_put(raw + 20, state->crc);
+}
+
+static void slot_state_decode(Slot_State_T *state, const uint8_t *raw)
+{
+    state->magic = le32_get(raw);
+    state->sequence = le32_get(raw + 4);
+    state->pending = le32_get(raw + 8);
+    state->size = le32_get(raw + 12);
+    state->trial = le32_get(raw + 16);
+    state->crc = le32_get(raw + 20);
+}
+
+static uint32_t slot_state_crc(const Slot_State_T *state)
+{
+    uint8_t raw[SLOT_STATE_SIZE];
+    Crc32_T crc;
+
+    slot_state_encode(raw, state);
+    crc.update(raw, offsetof(Slot_State_T, crc));
+    return crc.finalize();
+}
+
+static bool slot_state_valid(const Slot_State_T *state)
+{
+    return state->magic == SLOT_STATE_MAGIC && state->crc == slot_state_crc(state);
+}
+
+/* ERR_OK with pending and trial set to SLOT_NONE if there is no valid request */
+Error_T slot_state_read(Slot_State_T *state)
+{
+    if (!slot_state_cached) {
+        uint8_t raw[SLOT_STATE_SIZE];
+        Slot_State_T copy;
+
+        slot_state_cache.sequence = 0;
+        slot_state_cache.pending = SLOT_NONE;
+        slot_state_cache.size = 0;
+        slot_state_cache.trial = SLOT_TRIAL_NONE;
+        slot_state_copy = SLOT_NONE;
+
+        for (uint32_t i = 0; i < SLOT_STATE_COPIES; i++) {
+            if (!flash.read_N_bytes(sizeof(raw), SLOT_STATE_OFFSET + i * LAYOUT_SECTOR_SIZE, raw))
+                return ERR_FLASH_READ;
+
+            slot_state_decode(&copy, raw);
+            if (!slot_state_valid(&copy))
+                continue;
+
+            if (slot_state_copy == SLOT_NONE || copy.sequence > slot_state_cache.sequence) {
+                slot_state_cache = copy;
+                slot_state_copy = i;
+            }
+        }
+
+        slot_state_cached = true;
+    }
+
+    *state = slot_state_cache;
+    return ERR_OK;
+}
+
+/*
+ * goes through the flash directly, the bootloader api wraps it for the
+ * application. a new request starts its swap from the first step, whatever
+ * an earlier one left behind
+ */
+Error_T slot_state_write(uint32_t pending, uint32_t size, uint32_t trial)
+{
+    Slot_State_T state;
+    uint8_t raw[SLOT_STATE_SIZE];
+
+    Error_T error = slot_state_read(&state);
+    if (error != ERR_OK)
+        return error;
+
+    uint32_t copy = slot_state_copy == 0 ? 1 : 0;
+    uint32_t offset = SLOT_STATE_OFFSET + copy * LAYOUT_SECTOR_SIZE;
+
+    state.magic = SLOT_STATE_MAGIC;
+    state.sequence++;
+    state.pending = pending;
+    state.size = size;
+    state.trial = trial;
+    state.crc = slot_state_crc(&state);
+    slot_state_encode(raw, &state);
+
+    slot_state_invalidate();
+
+    uint32_t basepri = irq_commit_enter();
+
+    if (pending != SLOT_NONE && !flash.sector_erase(SLOT_SWAP_OFFSET, SLOT_SWAP_OFFSET))
+        error = ERR_FLASH_ERASE;
+    else if (!flash.sector_erase(offset, offset))
+        error = ERR_FLASH_ERASE;
+    else if (!flash.write_N_bytes(sizeof(raw), offset, raw))
+        error = ERR_FLASH_WRITE;
+
+    irq_commit_exit(basepri);
+    if (error != ERR_OK)
+        return error;
+
+    slot_state_cache = state;
+    slot_state_copy = copy;
+    slot_state_cached = true;
+    return ERR_OK;
+}
+
+/* no request and no trial, the image in the primary slot is the one to start */
+Error_T slot_state_clear(void)
+{
+    return slot_state_write(SLOT_NONE, 0, SLOT_TRIAL_NONE);
+}
+
+/* for writes to the metadata that don't go through the functions above */
+void slot_state_invalidate(void)
+{
+    slot_state_cached = false;
+}
+
+
+/* one sector of a swap step, erases again when retried */
+static Error_T slot_copy_sector(void *context)
+{
+    const Slot_Copy_T *copy = (const Slot_Copy_T *)context;
+
+    if (!flash.read_N_bytes(sizeof(slot_buffer), copy->from, slot_buffer))
+        return ERR_FLASH_READ;
+    if (!flash.sector_erase(copy->to, copy->to))
+        return ERR_FLASH_ERASE;
+    if (!flash.write_N_bytes(sizeof(slot_buffer), copy->to, slot_buffer))
+        return ERR_FLASH_WRITE;
+    if (!flash.read_N_bytes(sizeof(slot_verify), copy->to, slot_verify))
+        return ERR_FLASH_READ;
+    if (memcmp(slot_buffer, slot_verify, sizeof(slot_buffer)) != 0)
+        return ERR_FLASH_MISMATCH;
+
+    return ERR_OK;
+}
+
+static uint32_t slot_swap_crc(const Slot_Swap_T *swap)
+{
+    uint8_t raw[SLOT_SWAP_SIZE];
+    Crc32_T crc;
+
+    le32_put(raw, swap->magic);
+    le32_put(raw + 4, swap->kind);
+    le32_put(raw + 8, swap->size);
+    crc.update(raw, offsetof(Slot_Swap_T, crc));
+    return crc.finalize();
+}
+
+/* kind set to SLOT_SWAP_NONE if no swap was started */
+static Error_T slot_swap_read(Slot_Swap_T *swap)
+{
+    uint8_t raw[SLOT_SWAP_SIZE];
+
+    if (!flash.read_N_bytes(sizeof(raw), SLOT_SWAP_OFFSET, raw))
+        return ERR_FLASH_READ;
+
+    swap->magic = le32_get(raw);
+    swap->kind = le32_get(raw + 4);
+    swap->size = le32_get(raw + 8);
+    swap->crc = le32_get(raw + 12);
+
+    if (swap->magic != SLOT_SWAP_MAGIC || swap->crc != slot_swap_crc(swap))
+        swap->kind = SLOT_SWAP_NONE;
+
+    return ERR_OK;
+}
+
+static Error_T slot_swap_begin(Slot_Swap_T *swap, uint32_t kind, uint32_t size)
+{
+    uint8_t raw[SLOT_SWAP_SIZE];
+
+    swap->magic = SLOT_SWAP_MAGIC;
+    swap->kind = kind;
+    swap->size = size;
+    swap->crc = slot_swap_crc(swap);
+
+    le32_put(raw, swap->magic);
+    le32_put(raw + 4, swap->kind);
+    le32_put(raw + 8, swap->size);
+    le32_put(raw + 12, swap->crc);
+
+    uint32_t basepri = irq_commit_enter();
+    Error_T error = ERR_OK;
+
+    if (!flash.sector_erase(SLOT_SWAP_OFFSET, SLOT_SWAP_OFFSET))
+        error = ERR_FLASH_ERASE;
+    else if (!flash.write_N_bytes(sizeof(raw), SLOT_SWAP_OFFSET, raw))
+        error = ERR_FLASH_WRITE;
+
+    irq_commit_exit(basepri);
+    return error;
+}
+
+/* a finished swap, or one left behind by a reset right after its state was written */
+static Error_T slot_swap_end(void)
+{
+    uint8_t raw[SLOT_SWAP_SIZE];
+
+    if (!flash.read_N_bytes(sizeof(raw), SLOT_SWAP_OFFSET, raw))
+        return ERR_FLASH_READ;
+    if (le32_get(raw) == 0xFFFFFFFF)
+        return ERR_OK;
+
+    uint32_t basepri = irq_commit_enter();
+    bool erased = flash.sector_erase(SLOT_SWAP_OFFSET, SLOT_SWAP_OFFSET);
+    irq_commit_exit(basepri);
+
+    return erased ? ERR_OK : ERR_FLASH_ERASE;
+}
+
+static void slot_swap_step(uint32_t step, Slot_Copy_T *copy)
+{
+    uint32_t offset = step / SLOT_SWAP_STEPS * LAYOUT_SECTOR_SIZE;
+
+    switch (step % SLOT_SWAP_STEPS) {
+    case 0:
+        copy->from = Primary_Slot_T::base + offset;
+        copy->to = SLOT_SWAP_BUFFER;
+        break;
+    case 1:
+        copy->from = Secondary_Slot_T::base + offset;
+        copy->to = Primary_Slot_T::base + offset;
+        break;
+    default:
+        copy->from = SLOT_SWAP_BUFFER;
+        copy->to = Secondary_Slot_T::base + offset;
+        break;
+    }
+}
+
+/* the steps of a begun swap that aren't marked done yet */
+static Error_T slot_swap(const Slot_Swap_T *swap)
+{
+    uint32_t steps = swap->size / LAYOUT_SECTOR_SIZE * SLOT_SWAP_STEPS;
+    uint32_t step = 0;
+
+    if (!flash.read_N_bytes(steps, SLOT_SWAP_OFFSET + SLOT_SWAP_SIZE, slot_buffer))
+        return ERR_FLASH_READ;
+
+    /* a mark cut short by a reset reads as neither, its step was done */
+    while (step < steps && slot_buffer[step] != 0xFF)
+        step++;
+
+    for (; step < steps; step++) {
+        Slot_Copy_T copy;
+        uint8_t done = 0;
+
+        slot_swap_step(step, &copy);
+
+        Error_T error = retry(&retry_flash, slot_copy_sector, &copy);
+        if (error != ERR_OK)
+            return error;
+        if (!flash.write_N_bytes(1, SLOT_SWAP_OFFSET + SLOT_SWAP_SIZE + step, &done))
+            return ERR_FLASH_WRITE;
+    }
+
+    return ERR_OK;
+}
+
+/* so verify checks what is in the primary slot now */
+static Error_T slot_manifest(uint32_t size)
+{
+    Manifest_T manifest = {};
+
+    manifest_add_range(&manifest, Primary_Slot_T::base, size);
+    return manifest_write(&manifest);
+}
+
+/* the image the install pushes out has to survive whole for a revert */
+static uint32_t slot_swap_size(uint32_t size)
+{
+    uint8_t raw[IMAGE_HEADER_SIZE];
+    Image_Header_T header;
+
+    if (!flash.read_N_bytes(sizeof(raw), Primary_Slot_T::base, raw))
+        return size;
+
+    image_header_decode(&header, raw);
+    if (image_header_check(&header) != ERR_OK)
+        return size;
+
+    uint32_t used = (header.length + LAYOUT_SECTOR_SIZE - 1) / LAYOUT_SECTOR_SIZE * LAYOUT_SECTOR_SIZE;
+    return used > size ? used : size;
+}
+
+/**
+ * @brief   run the checks an install starts with on the image in the secondary slot
+ * @param   size    bytes marked for the install
+ * @param   header  set to the header of the staged image
+ * @retval  ERR_FLASH_READ, or an ERR_IMAGE_ code if the secondary slot doesn't
+ *          start with a valid header for an image within size
+ */
+Error_T slot_staged_check(uint32_t size, Image_Header_T *header)
+{
+    /* the header and the largest metadata area that fits in front of the vectors */
+    if (!flash.read_N_bytes(IMAGE_HEADER_SIZE + IMAGE_TLV_MAX, Secondary_Slot_T::base, slot_buffer))
+        return ERR_FLASH_READ;
+
+    image_header_decode(header, slot_buffer);
+
+    Image_Target_T target;
+    image_target(&target);
+
+    Error_T error = image_header_check(header);
+    if (error == ERR_OK)
+        error = image_header_match(header, &target);
+    if (error == ERR_OK)
+        error = image_tlv_check(header, slot_buffer + IMAGE_HEADER_SIZE);
+    if (error == ERR_OK && header->length > size)
+        error = ERR_IMAGE_LENGTH;
+
+    return error;
+}
+
+/* the primary slot hashes to what the staged image does, over the bytes its header covers */
+bool slot_staged_installed(const Image_Header_T *header)
+{
+    Manifest_T staged = {};
+    Manifest_T installed = {};
+
+    manifest_add_range(&staged, Secondary_Slot_T::base, header->length);
+    manifest_add_range(&installed, Primary_Slot_T::base, header->length);
+
+    if (manifest_hash(&staged, installed.sha256) != ERR_OK)
+        return false;
+
+    return manifest_verify(&installed) == ERR_OK;
+}
+
+/**
+ * @brief   swap the first size bytes of the secondary slot with the primary slot
+ * @param   size    bytes to install, rounded up to whole sectors
+ * @param   same    set if the staged image is the one installed already. the
+ *                  request is dropped without erasing anything
+ * @retval  an ERR_IMAGE_ code, before anything is erased, if the secondary
+ *          slot doesn't start with a valid header for an image within size
+ * @note    the request is only cleared once this succeeded, so a reset
+ *          halfway through carries on with the swap on the next boot. each
+ *          sector is retried on transient flash errors, see retry_flash. the
+ *          previous image ends up in the secondary slot and the new one
+ *          starts its trial. the manifest of the last upload is replaced by
+ *          one covering the swap, so verify checks the installed image
+ */
+Error_T slot_install(uint32_t size, bool *same)
+{
+    *same = false;
+
+    if (size == 0 || size > Secondary_Slot_T::len || size > Primary_Slot_T::len)
+        return ERR_OUT_OF_RANGE;
+
+    Slot_Swap_T swap;
+
+    Error_T error = slot_swap_read(&swap);
+    if (error != ERR_OK)
+        return error;
+
+    /* the header of a swap under way may be in either slot by now */
+    if (swap.kind != SLOT_SWAP_INSTALL) {
+        Image_Header_T header;
+
+        error = slot_staged_check(size, &header);
+        if (error != ERR_OK)
+            return error;
+
+        /* fleet tooling pushing the same version again */
+        if (slot_staged_installed(&header)) {
+            *same = true;
+            return slot_state_clear();
+        }
+
+        error = slot_swap_begin(&swap, SLOT_SWAP_INSTALL, slot_swap_size(size));
+        if (error != ERR_OK)
+            return error;
+    }
+
+    error = slot_swap(&swap);
+    if (error == ERR_OK)
+        error = slot_manifest(swap.size);
+    if (error == ERR_OK)
+        error = slot_state_write(SLOT_NONE, swap.size, SLOT_TRIAL_INSTALLED);
+    if (error == ERR_OK)
+        error = slot_swap_end();
+
+    return error;
+}
+
+/**
+ * @brief   swap an image that didn't confirm its trial back out
+ * @param   state   with the trial SLOT_TRIAL_TESTING, or SLOT_TRIAL_REVERTING
+ *                  to carry on after a reset
+ * @retval  an ERR_IMAGE_ code if the secondary slot holds no image to go back
+ *          to, before anything is changed
+ */
+Error_T slot_revert(const Slot_State_T *state)
+{
+    Slot_Swap_T swap;
+
+    Error_T error = slot_swap_read(&swap);
+    if (error != ERR_OK)
+        return error;
+
+    if (state->trial == SLOT_TRIAL_TESTING) {
+        uint8_t raw[IMAGE_HEADER_SIZE];
+        Image_Header_T header;
+
+        if (!flash.read_N_bytes(sizeof(raw), Secondary_Slot_T::base, raw))
+            return ERR_FLASH_READ;
+
+        image_header_decode(&header, raw);
+        error = image_header_check(&header);
+        if (error != ERR_OK)
+            return error;
+
+        error = slot_state_write(SLOT_NONE, state->size, SLOT_TRIAL_REVERTING);
+        if (error != ERR_OK)
+            return error;
+        swap.kind = SLOT_SWAP_NONE;
+    }
+
+    if (swap.kind != SLOT_SWAP_REVERT) {
+        error = slot_swap_begin(&swap, SLOT_SWAP_REVERT, state->size);
+        if (error != ERR_OK)
+            return error;
+    }
+
+    error = slot_swap(&swap);
+    if (error == ERR_OK)
+        error = slot_manifest(swap.size);
+    if (error == ERR_OK)
+        error = slot_state_clear();
+    if (error == ERR_OK)
+        error = slot_swap_end();
+
+    return error;
+}
+
+/* the one start an installed image gets before it has to confirm */
+Error_T slot_trial_start(void)
+{
+    Slot_State_T state;
+
+    Error_T error = slot_state_read(&state);
+    if (error != ERR_OK || state.pending != SLOT_NONE || state.trial != SLOT_TRIAL_INSTALLED)
+        return error;
+
+    return slot_state_write(SLOT_NONE, state.size, SLOT_TRIAL_TESTING);
+}
+
+/* the image in the primary slot stays, a pending request is left alone */
+Error_T slot_confirm(void)
+{
+    Slot_State_T state;
+
+    Error_T error = slot_state_read(&state);
+    if (error != ERR_OK || state.pending != SLOT_NONE || state.trial == SLOT_TRIAL_NONE)
+        return error;
+
+    return slot_state_clear();
+}
+
+/* the primary slot is untouched if the staged image is refused, and it would be refused again */
+static bool slot_refused(Error_T error)
+{
+    return (error & 0xF0) == ERR_IMAGE_MAGIC || error == ERR_OUT_OF_RANGE;
+}
+
+/**
+ * @brief   carry out what the slot state asks for at boot: an install, or the
+ *          revert of a trial that wasn't confirmed
+ * @param   error   set to the error of the install or revert
+ * @retval  what was done. the primary slot is only safe to start after
+ *          anything but SLOT_ACTION_FAILED
+ */
+Slot_Action_T slot_update(Error_T *error)
+{
+    Slot_State_T state;
+
+    *error = slot_state_read(&state);
+    if (*error != ERR_OK)
+        return SLOT_ACTION_FAILED;
+
+    if (state.pending != SLOT_NONE) {
+        bool same;
+
+        *error = slot_install(state.size, &same);
+        if (*error == ERR_OK)
+            return same ? SLOT_ACTION_SAME : SLOT_ACTION_INSTALLED;
+
+        if (!slot_refused(*error))
+            return SLOT_ACTION_FAILED;
+
+        slot_state_clear();
+        return SLOT_ACTION_REFUSED;
+    }
+
+    if (state.trial == SLOT_TRIAL_TESTING || state.trial == SLOT_TRIAL_REVERTING) {
+        *error = slot_revert(&state);
+        if (*error == ERR_OK)
+            return SLOT_ACTION_REVERTED;
+
+        /* nothing valid was pushed out by the install, the image keeps running unconfirmed */
+        if (state.trial == SLOT_TRIAL_TESTING && (*error & 0xF0) == ERR_IMAGE_MAGIC) {
+            slot_state_clear();
+            return SLOT_ACTION_KEPT;
+        }
+
+        return SLOT_ACTION_FAILED;
+    }
+
+    *error = slot_swap_end();
+    return SLOT_ACTION_NONE;
+}
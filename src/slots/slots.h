@@ -0,0 +1,98 @@
+#ifndef SLOTS_H_
+#define SLOTS_H_
+
+#include <stddef.h>
+#include <stdint.h>
+#include "errors.h"
+#include "layout_map.h"
+#include "image_header.h"
+
+/* second and third metadata sector, the first one holds the upload manifest */
+#define SLOT_STATE_OFFSET   (LAYOUT_METADATA_BASE + LAYOUT_SECTOR_SIZE)
+#define SLOT_STATE_COPIES   2
+#define SLOT_STATE_MAGIC    0x534C4F54
+
+/* progress of a swap, and the sector each swapped sector passes through */
+#define SLOT_SWAP_OFFSET    (LAYOUT_METADATA_BASE + 3 * LAYOUT_SECTOR_SIZE)
+#define SLOT_SWAP_BUFFER    (LAYOUT_METADATA_BASE + 4 * LAYOUT_SECTOR_SIZE)
+#define SLOT_SWAP_MAGIC     0x50415753 /* "SWAP" */
+
+#define SLOT_NONE           0xFFFFFFFF
+
+/*
+ * trial of an installed image. it is started once as SLOT_TRIAL_TESTING and
+ * has to call confirm in the bootloader api before the next reset, or the
+ * bootloader swaps the previous image back in.
+ */
+#define SLOT_TRIAL_NONE         0xFFFFFFFF
+#define SLOT_TRIAL_INSTALLED    1
+#define SLOT_TRIAL_TESTING      2
+#define SLOT_TRIAL_REVERTING    3
+
+/*
+ * install request left for the next boot, and the trial of the image it
+ * installed. it is written to the older of two sectors in turn, the copy
+ * with the higher sequence is the state. a write cut short by a reset leaves
+ * the state as it was before, two erased sectors read as neither.
+ */
+typedef struct {
+    uint32_t magic;
+    uint32_t sequence;  //one more than the copy it replaced
+    uint32_t pending;   //slot to install into the primary slot, SLOT_NONE if nothing
+    uint32_t size;      //bytes to copy, a multiple of the sector size, or swapped for the trial
+    uint32_t trial;     //SLOT_TRIAL_
+    uint32_t crc;       //crc32 of the fields above
+} Slot_State_T;
+
+#define SLOT_STATE_SIZE     24
+
+static_assert(sizeof(Slot_State_T) == SLOT_STATE_SIZE, "slot state has padding");
+static_assert(offsetof(Slot_State_T, crc) == 20, "slot state layout changed");
+
+#define SLOT_SWAP_NONE      0
+#define SLOT_SWAP_INSTALL   1
+#define SLOT_SWAP_REVERT    2
+
+/*
+ * head of the swap progress sector. a byte per step follows it, programmed
+ * to 0 once the step is done: the primary sector goes to the buffer, the
+ * secondary sector to the primary and the buffer to the secondary. every step
+ * reads from a sector the steps since haven't touched, so an interrupted swap
+ * carries on at the first step that isn't marked.
+ */
+typedef struct {
+    uint32_t magic;
+    uint32_t kind;      //SLOT_SWAP_INSTALL or SLOT_SWAP_REVERT
+    uint32_t size;      //bytes swapped, a multiple of the sector size
+    uint32_t crc;       //crc32 of the fields above
+} Slot_Swap_T;
+
+#define SLOT_SWAP_SIZE      16
+#define SLOT_SWAP_STEPS     3
+
+static_assert(sizeof(Slot_Swap_T) == SLOT_SWAP_SIZE, "swap progress has padding");
+
+/* what slot_update() did at boot */
+typedef enum {
+    SLOT_ACTION_NONE,       //nothing asked for
+    SLOT_ACTION_INSTALLED,  //the staged image is in the primary slot, on trial
+    SLOT_ACTION_SAME,       //the staged image was installed already, the request is dropped
+    SLOT_ACTION_REFUSED,    //the staged image was refused and the request dropped
+    SLOT_ACTION_REVERTED,   //an unconfirmed image was swapped back out
+    SLOT_ACTION_KEPT,       //an unconfirmed image stays, there is nothing to go back to
+    SLOT_ACTION_FAILED,     //a flash error, carried on next boot. the primary slot may be half swapped
+} Slot_Action_T;
+
+Error_T slot_state_read(Slot_State_T *state);
+Error_T slot_state_write(uint32_t pending, uint32_t size, uint32_t trial);
+Error_T slot_state_clear(void);
+void slot_state_invalidate(void);
+Error_T slot_staged_check(uint32_t size, Image_Header_T *header);
+bool slot_staged_installed(const Image_Header_T *header);
+Error_T slot_install(uint32_t size, bool *same);
+Error_T slot_revert(const Slot_State_T *state);
+Error_T slot_trial_start(void);
+Error_T slot_confirm(void);
+Slot_Action_T slot_update(Error_T *error);
+
+#endif
@@ -43,6 +43,10 @@ defined in linker script */
 .word  _sbss
 /* end address for the .bss section. defined in linker script */
 .word  _ebss
+/* start address for the .shared_ram section. defined in linker script */
+.word  _sshared
+/* end address for the .shared_ram section. defined in linker script */
+.word  _eshared
 /* stack used for SystemInit_ExtMemCtl; always internal RAM used */
 
 /**
@@ -95,6 +99,20 @@ LoopFillZerobss:
   cmp r2, r4
   bcc FillZerobss
 
+/* Zero fill the state shared with the application */
+  ldr r2, =_sshared
+  ldr r4, =_eshared
+  movs r3, #0
+  b LoopFillZeroShared
+
+FillZeroShared:
+  str  r3, [r2]
+  adds r2, r2, #4
+
+LoopFillZeroShared:
+  cmp r2, r4
+  bcc FillZeroShared
+
 /* Call static constructors */
     bl __libc_init_array
 /* Call the application's entry point.*/
@@ -0,0 +1,23 @@
+#include "test.h"
+
+int test_failures;
+
+int main(void)
+{
+    test_boot_select();
+    test_crypto();
+    test_hexfile();
+    test_image_header();
+    test_layout();
+    test_manifest();
+    test_retry();
+    test_slots();
+
+    if (test_failures) {
+        printf("%d checks failed\n", test_failures);
+        return 1;
+    }
+
+    printf("all checks passed\n");
+    return 0;
+}
@@ -0,0 +1,81 @@
+#include "w25q.h"
+#include "rng.h"
+#include "rtc.h"
+#include <string.h>
+
+Flash_T flash;
+RNG_HandleTypeDef rng;
+uint32_t stub_delay_ms;
+
+static bool flash_in_range(uint32_t address, uint32_t N)
+{
+    return address < LAYOUT_MEMORY_SIZE && N <= LAYOUT_MEMORY_SIZE - address;
+}
+
+/* past the cut nothing reaches the flash any more, until the test powers it up again */
+static bool flash_powered(Flash_T *flash)
+{
+    return flash->cut_after == 0 || flash->ops++ < flash->cut_after;
+}
+
+uint32_t Flash_T::size(void)
+{
+    return LAYOUT_MEMORY_SIZE;
+}
+
+bool Flash_T::read_N_bytes(uint32_t N, uint32_t address, uint8_t *rbuffer)
+{
+    if (fail_reads || !flash_in_range(address, N))
+        return false;
+
+    memcpy(rbuffer, &memory[address], N);
+    return true;
+}
+
+bool Flash_T::write_N_bytes(uint32_t N, uint32_t address, uint8_t *sbuffer)
+{
+    if (!flash_in_range(address, N) || !flash_powered(this))
+        return false;
+
+    for (uint32_t i = 0; i < N; i++) {
+        memory[address + i] &= sbuffer[i];
+    }
+
+    return true;
+}
+
+bool Flash_T::sector_erase(uint32_t start, uint32_t end)
+{
+    if (start > end || !flash_in_range(end, 1) || !flash_powered(this))
+        return false;
+
+    start -= start % LAYOUT_SECTOR_SIZE;
+    end += LAYOUT_SECTOR_SIZE - 1 - end % LAYOUT_SECTOR_SIZE;
+    memset(&memory[start], 0xFF, end - start + 1);
+    return true;
+}
+
+void HAL_Delay(uint32_t Delay)
+{
+    stub_delay_ms += Delay;
+}
+
+/* no jitter, so the backoff stays predictable */
+uint32_t rng_jitter(RNG_HandleTypeDef *handle, uint32_t max)
+{
+    (void)handle;
+    (void)max;
+    return 0;
+}
+
+/* a bench board, images don't expire */
+bool rtc_unix_time(uint32_t *seconds)
+{
+    (void)seconds;
+    return false;
+}
+
+bool rtc_production_locked(void)
+{
+    return false;
+}
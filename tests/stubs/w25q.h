@@ -0,0 +1,28 @@
+#ifndef W25Q_H_
+#define W25Q_H_
+
+#include <stdint.h>
+#include "layout_map.h"
+
+/* the external flash as a nor array in ram: erase sets bits, programming only clears them */
+class Flash_T
+{
+public:
+    uint8_t memory[LAYOUT_MEMORY_SIZE];
+    bool fail_reads;
+    uint32_t cut_after;     //erases and writes that go through before the power is cut, 0 for no cut
+    uint32_t ops;           //erases and writes so far
+
+    uint32_t size(void);
+
+    bool read_N_bytes(uint32_t N, uint32_t address, uint8_t *rbuffer);
+    bool write_N_bytes(uint32_t N, uint32_t address, uint8_t *sbuffer);
+    bool sector_erase(uint32_t start, uint32_t end);
+};
+
+extern Flash_T flash;
+
+/* total of the HAL_Delay() calls */
+extern uint32_t stub_delay_ms;
+
+#endif
@@ -0,0 +1,26 @@
+#ifndef TEST_H_
+#define TEST_H_
+
+#include <stdio.h>
+
+/* checks keep going after a failure, main() reports the count */
+extern int test_failures;
+
+#define CHECK(condition)                                                        \
+    do {                                                                        \
+        if (!(condition)) {                                                     \
+            printf("%s:%d: CHECK(%s) failed\n", __FILE__, __LINE__, #condition); \
+            test_failures++;                                                    \
+        }                                                                       \
+    } while (0)
+
+void test_boot_select(void);
+void test_crypto(void);
+void test_hexfile(void);
+void test_image_header(void);
+void test_layout(void);
+void test_manifest(void);
+void test_retry(void);
+void test_slots(void);
+
+#endif
@@ -0,0 +1,307 @@
+#include "test.h"
+#include "boot_select.h"
+#include "layout.h"
+#include "image_header.h"
+#include "manifest.h"
+#include "crc32.h"
+#include "le.h"
+#include "w25q.h"
+#include "stm32h7xx_hal.h"
+#include <string.h>
+
+#define IMAGE_LEN       LAYOUT_SECTOR_SIZE
+#define IMAGE_STACK     (D1_DTCMRAM_BASE + 128 * 1024)
+#define IMAGE_RESET     (QSPI_BASE + IMAGE_ENTRY_ALIGN + 0x101)
+
+/* a header for IMAGE_LEN bytes and a vector table that passes the checks, at base */
+static void image_write(uint32_t base, uint32_t version)
+{
+    Image_Header_T header = {};
+    uint8_t raw[IMAGE_HEADER_SIZE];
+    Crc32_T crc;
+
+    memset(&flash.memory[base], 0x5A, IMAGE_LEN);
+
+    header.magic = IMAGE_HEADER_MAGIC;
+    header.version = version;
+    header.length = IMAGE_LEN;
+    header.entry_offset = IMAGE_ENTRY_ALIGN;
+    image_header_encode(raw, &header);
+    crc.update(raw, offsetof(Image_Header_T, header_crc));
+    header.header_crc = crc.finalize();
+    image_header_encode(&flash.memory[base], &header);
+
+    le32_put(&flash.memory[base + IMAGE_ENTRY_ALIGN], IMAGE_STACK);
+    le32_put(&flash.memory[base + IMAGE_ENTRY_ALIGN + 4], IMAGE_RESET);
+}
+
+/*
+ * metadata sectors as the bootloader leaves them, byte for byte, so a change
+ * to the encoding shows up here and not on boards in the field
+ */
+static const uint8_t state_pending[] = {
+    0x54, 0x4F, 0x4C, 0x53, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+    0x00, 0x10, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xB5, 0xCE, 0xC0, 0xA9,
+};
+
+static const uint8_t state_idle[] = {
+    0x54, 0x4F, 0x4C, 0x53, 0x01, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+    0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x84, 0x27, 0x91, 0xD1,
+};
+
+/* state_pending with the next sequence and a crc that doesn't match, a write cut short */
+static const uint8_t state_pending_torn[] = {
+    0x54, 0x4F, 0x4C, 0x53, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+    0x00, 0x10, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x46, 0x7A, 0x08, 0x80,
+};
+
+static const uint8_t state_installed[] = {
+    0x54, 0x4F, 0x4C, 0x53, 0x02, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+    0x00, 0x10, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3E, 0x48, 0x80, 0xF9,
+};
+
+static const uint8_t state_testing[] = {
+    0x54, 0x4F, 0x4C, 0x53, 0x03, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+    0x00, 0x10, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x41, 0x76, 0x5D, 0x45,
+};
+
+static const uint8_t state_reverting[] = {
+    0x54, 0x4F, 0x4C, 0x53, 0x04, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+    0x00, 0x10, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x51, 0xE9, 0x18, 0x00,
+};
+
+/* swap progress heads and their step marks, one sector swapped */
+static const uint8_t swap_install_buffered[SLOT_SWAP_SIZE + SLOT_SWAP_STEPS] = {
+    0x53, 0x57, 0x41, 0x50, 0x01, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00,
+    0x32, 0x08, 0x13, 0xB2, 0x00, 0xFF, 0xFF,
+};
+
+static const uint8_t swap_install_copied[SLOT_SWAP_SIZE + SLOT_SWAP_STEPS] = {
+    0x53, 0x57, 0x41, 0x50, 0x01, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00,
+    0x32, 0x08, 0x13, 0xB2, 0x00, 0x00, 0xFF,
+};
+
+static const uint8_t swap_revert_buffered[SLOT_SWAP_SIZE + SLOT_SWAP_STEPS] = {
+    0x53, 0x57, 0x41, 0x50, 0x02, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00,
+    0xD1, 0x0F, 0x9C, 0x3C, 0x00, 0xFF, 0xFF,
+};
+
+/* a flipped bit in the size, the crc doesn't cover it any more */
+static const uint8_t swap_install_torn[SLOT_SWAP_SIZE + SLOT_SWAP_STEPS] = {
+    0x53, 0x57, 0x41, 0x50, 0x01, 0x00, 0x00, 0x00, 0x80, 0x10, 0x00, 0x00,
+    0x32, 0x08, 0x13, 0xB2, 0xFF, 0xFF, 0xFF,
+};
+
+enum Corpus_Image_T {
+    CORPUS_ERASED,
+    CORPUS_OLD,             //version 1
+    CORPUS_NEW,             //version 2
+    CORPUS_NEW_BAD_HEADER,  //the header crc doesn't match
+    CORPUS_NEW_BAD_VECTORS, //a valid header in front of a stack pointer outside the ram
+};
+
+struct Corpus_T {
+    const char *name;
+    const uint8_t *state[SLOT_STATE_COPIES];    //first bytes of each state sector, NULL if erased
+    const uint8_t *swap;                        //first bytes of the swap progress sector, NULL if erased
+    Corpus_Image_T primary;
+    Corpus_Image_T secondary;
+    Corpus_Image_T buffer;
+
+    /* what a boot makes of it */
+    Slot_Action_T action;
+    bool boot;
+    Corpus_Image_T primary_after;
+    Corpus_Image_T secondary_after;
+    uint32_t trial_after;
+};
+
+static const Corpus_T corpus[] = {
+    {"fresh device", {NULL, NULL}, NULL, CORPUS_ERASED, CORPUS_ERASED, CORPUS_ERASED,
+     SLOT_ACTION_NONE, false, CORPUS_ERASED, CORPUS_ERASED, SLOT_TRIAL_NONE},
+    {"factory image", {NULL, NULL}, NULL, CORPUS_OLD, CORPUS_ERASED, CORPUS_ERASED,
+     SLOT_ACTION_NONE, true, CORPUS_OLD, CORPUS_ERASED, SLOT_TRIAL_NONE},
+    {"pending install", {state_pending, NULL}, NULL, CORPUS_OLD, CORPUS_NEW, CORPUS_ERASED,
+     SLOT_ACTION_INSTALLED, true, CORPUS_NEW, CORPUS_OLD, SLOT_TRIAL_INSTALLED},
+    {"pending install of the running image", {state_pending, NULL}, NULL, CORPUS_OLD, CORPUS_OLD, CORPUS_ERASED,
+     SLOT_ACTION_SAME, true, CORPUS_OLD, CORPUS_OLD, SLOT_TRIAL_NONE},
+    {"pending install with a corrupted header", {state_pending, NULL}, NULL, CORPUS_OLD, CORPUS_NEW_BAD_HEADER,
+     CORPUS_ERASED, SLOT_ACTION_REFUSED, true, CORPUS_OLD, CORPUS_NEW_BAD_HEADER, SLOT_TRIAL_NONE},
+    {"pending install with bad vectors", {state_pending, NULL}, NULL, CORPUS_OLD, CORPUS_NEW_BAD_VECTORS,
+     CORPUS_ERASED, SLOT_ACTION_REVERTED, true, CORPUS_OLD, CORPUS_NEW_BAD_VECTORS, SLOT_TRIAL_NONE},
+    {"install cut after the buffer", {state_pending, NULL}, swap_install_buffered, CORPUS_OLD, CORPUS_NEW,
+     CORPUS_OLD, SLOT_ACTION_INSTALLED, true, CORPUS_NEW, CORPUS_OLD, SLOT_TRIAL_INSTALLED},
+    {"install cut after the primary", {state_pending, NULL}, swap_install_copied, CORPUS_NEW, CORPUS_NEW,
+     CORPUS_OLD, SLOT_ACTION_INSTALLED, true, CORPUS_NEW, CORPUS_OLD, SLOT_TRIAL_INSTALLED},
+    {"install cut while its progress was written", {state_pending, NULL}, swap_install_torn, CORPUS_OLD,
+     CORPUS_NEW, CORPUS_ERASED, SLOT_ACTION_INSTALLED, true, CORPUS_NEW, CORPUS_OLD, SLOT_TRIAL_INSTALLED},
+    {"request cut while it was written", {state_idle, state_pending_torn}, NULL, CORPUS_OLD, CORPUS_NEW,
+     CORPUS_ERASED, SLOT_ACTION_NONE, true, CORPUS_OLD, CORPUS_NEW, SLOT_TRIAL_NONE},
+    {"corrupted state, no copy left", {state_pending_torn, NULL}, NULL, CORPUS_OLD, CORPUS_NEW, CORPUS_ERASED,
+     SLOT_ACTION_NONE, true, CORPUS_OLD, CORPUS_NEW, SLOT_TRIAL_NONE},
+    {"installed, not started yet", {state_idle, state_installed}, NULL, CORPUS_NEW, CORPUS_OLD, CORPUS_ERASED,
+     SLOT_ACTION_NONE, true, CORPUS_NEW, CORPUS_OLD, SLOT_TRIAL_INSTALLED},
+    {"trial not confirmed", {state_testing, state_installed}, NULL, CORPUS_NEW, CORPUS_OLD, CORPUS_ERASED,
+     SLOT_ACTION_REVERTED, true, CORPUS_OLD, CORPUS_NEW, SLOT_TRIAL_NONE},
+    {"revert cut after the buffer", {state_testing, state_reverting}, swap_revert_buffered, CORPUS_NEW,
+     CORPUS_OLD, CORPUS_NEW, SLOT_ACTION_REVERTED, true, CORPUS_OLD, CORPUS_NEW, SLOT_TRIAL_NONE},
+    {"rollback exhausted", {state_testing, NULL}, NULL, CORPUS_NEW, CORPUS_ERASED, CORPUS_ERASED,
+     SLOT_ACTION_KEPT, true, CORPUS_NEW, CORPUS_ERASED, SLOT_TRIAL_NONE},
+};
+
+static void select_erased(void)
+{
+    memset(flash.memory, 0xFF, sizeof(flash.memory));
+    flash.cut_after = 0;
+    slot_state_invalidate();
+    manifest_invalidate();
+}
+
+static void test_boot_check_vectors(void)
+{
+    CHECK(boot_check_vectors(IMAGE_STACK, IMAGE_RESET, IMAGE_LEN) == BOOT_CHECK_OK);
+
+    /* the initial stack pointer may sit right past the end of a ram */
+    CHECK(boot_check_vectors(D3_SRAM_BASE + 0x100, IMAGE_RESET, IMAGE_LEN) == BOOT_CHECK_OK);
+    CHECK(boot_check_vectors(LAYOUT_SHARED_RAM_BASE + 4, IMAGE_RESET, IMAGE_LEN) == BOOT_CHECK_STACK_OUT_OF_RAM);
+    CHECK(boot_check_vectors(D1_DTCMRAM_BASE, IMAGE_RESET, IMAGE_LEN) == BOOT_CHECK_STACK_OUT_OF_RAM);
+    CHECK(boot_check_vectors(0xFFFFFFFF, 0xFFFFFFFF, IMAGE_LEN) == BOOT_CHECK_STACK_OUT_OF_RAM);
+    CHECK(boot_check_vectors(IMAGE_STACK - 2, IMAGE_RESET, IMAGE_LEN) == BOOT_CHECK_STACK_UNALIGNED);
+
+    CHECK(boot_check_vectors(IMAGE_STACK, IMAGE_RESET - 1, IMAGE_LEN) == BOOT_CHECK_RESET_NOT_THUMB);
+    CHECK(boot_check_vectors(IMAGE_STACK, 0x08000101, IMAGE_LEN) == BOOT_CHECK_RESET_OUT_OF_SLOT);
+    CHECK(boot_check_vectors(IMAGE_STACK, QSPI_BASE + Primary_Slot_T::end + 1, IMAGE_LEN) ==
+          BOOT_CHECK_RESET_OUT_OF_SLOT);
+    CHECK(boot_check_vectors(IMAGE_STACK, QSPI_BASE + IMAGE_LEN + 1, IMAGE_LEN) == BOOT_CHECK_RESET_OUT_OF_IMAGE);
+    CHECK(boot_check_vectors(IMAGE_STACK, QSPI_BASE + IMAGE_LEN - 1, IMAGE_LEN) == BOOT_CHECK_OK);
+}
+
+static void test_boot_decide(void)
+{
+    Boot_Decision_T decision;
+
+    /* a board fresh from the factory has nothing to start */
+    select_erased();
+    boot_decide(&decision, true);
+    CHECK(decision.state == ERR_OK);
+    CHECK(decision.pending == SLOT_NONE && decision.trial == SLOT_TRIAL_NONE);
+    CHECK(decision.action == SLOT_ACTION_NONE);
+    CHECK(decision.primary == ERR_IMAGE_MAGIC);
+    CHECK(decision.secondary == ERR_IMAGE_MAGIC);
+    CHECK(!decision.boot);
+
+    select_erased();
+    image_write(Primary_Slot_T::base, 3);
+    image_write(Secondary_Slot_T::base, 2);
+    boot_decide(&decision, true);
+    CHECK(decision.primary == ERR_OK && decision.primary_version == 3);
+    CHECK(decision.secondary == ERR_OK && decision.secondary_version == 2);
+    CHECK(decision.boot);
+
+    /* the header is fine, the vector table isn't */
+    le32_put(&flash.memory[Primary_Slot_T::base + IMAGE_ENTRY_ALIGN + 4], IMAGE_RESET - 1);
+    boot_decide(&decision, true);
+    CHECK(decision.primary == ERR_BOOT_RESET_NOT_THUMB);
+    CHECK(!decision.boot);
+
+    /* installed with a vector table that can't be started, so it is reverted right away */
+    select_erased();
+    image_write(Primary_Slot_T::base, 3);
+    image_write(Secondary_Slot_T::base, 4);
+    le32_put(&flash.memory[Secondary_Slot_T::base + IMAGE_ENTRY_ALIGN], 0xFFFFFFFF);
+    CHECK(slot_state_write(1, IMAGE_LEN, SLOT_TRIAL_NONE) == ERR_OK);
+    boot_decide(&decision, true);
+    CHECK(decision.action == SLOT_ACTION_REVERTED);
+    CHECK(decision.primary == ERR_OK && decision.primary_version == 3);
+    CHECK(decision.secondary == ERR_OK && decision.secondary_version == 4);
+    CHECK(decision.boot);
+
+    select_erased();
+    image_write(Primary_Slot_T::base, 3);
+    flash.fail_reads = true;
+    boot_decide(&decision, true);
+    flash.fail_reads = false;
+    CHECK(decision.state == ERR_FLASH_READ);
+    CHECK(decision.action == SLOT_ACTION_FAILED);
+    CHECK(!decision.boot);
+}
+
+static void corpus_image(uint32_t base, Corpus_Image_T image)
+{
+    switch (image) {
+    case CORPUS_ERASED:
+        memset(&flash.memory[base], 0xFF, IMAGE_LEN);
+        break;
+    case CORPUS_OLD:
+        image_write(base, 1);
+        break;
+    case CORPUS_NEW:
+        image_write(base, 2);
+        break;
+    case CORPUS_NEW_BAD_HEADER:
+        image_write(base, 2);
+        flash.memory[base + offsetof(Image_Header_T, header_crc)] ^= 1;
+        break;
+    case CORPUS_NEW_BAD_VECTORS:
+        image_write(base, 2);
+        le32_put(&flash.memory[base + IMAGE_ENTRY_ALIGN], 0xFFFFFFFF);
+        break;
+    }
+}
+
+static bool corpus_holds(uint32_t base, Corpus_Image_T image)
+{
+    static uint8_t expected[IMAGE_LEN];
+
+    memcpy(expected, &flash.memory[base], IMAGE_LEN);
+    corpus_image(base, image);
+
+    bool same = memcmp(expected, &flash.memory[base], IMAGE_LEN) == 0;
+    memcpy(&flash.memory[base], expected, IMAGE_LEN);
+    return same;
+}
+
+/* every entry is loaded into an erased flash and booted once */
+static void test_boot_corpus(void)
+{
+    for (const Corpus_T &entry : corpus) {
+        int failures = test_failures;
+        Boot_Decision_T decision;
+        Slot_State_T state;
+
+        select_erased();
+        for (uint32_t i = 0; i < SLOT_STATE_COPIES; i++) {
+            if (entry.state[i])
+                memcpy(&flash.memory[SLOT_STATE_OFFSET + i * LAYOUT_SECTOR_SIZE], entry.state[i], SLOT_STATE_SIZE);
+        }
+        if (entry.swap)
+            memcpy(&flash.memory[SLOT_SWAP_OFFSET], entry.swap, SLOT_SWAP_SIZE + SLOT_SWAP_STEPS);
+
+        corpus_image(Primary_Slot_T::base, entry.primary);
+        corpus_image(Secondary_Slot_T::base, entry.secondary);
+        corpus_image(SLOT_SWAP_BUFFER, entry.buffer);
+
+        boot_decide(&decision, true);
+        CHECK(decision.state == ERR_OK);
+        CHECK(decision.action == entry.action);
+        CHECK(decision.boot == entry.boot);
+        CHECK(corpus_holds(Primary_Slot_T::base, entry.primary_after));
+        CHECK(corpus_holds(Secondary_Slot_T::base, entry.secondary_after));
+
+        /* from the flash, as the next boot sees it */
+        slot_state_invalidate();
+        CHECK(slot_state_read(&state) == ERR_OK);
+        CHECK(state.pending == SLOT_NONE);
+        CHECK(state.trial == entry.trial_after);
+
+        if (test_failures != failures)
+            printf("  in corpus entry \"%s\"\n", entry.name);
+    }
+}
+
+void test_boot_select(void)
+{
+    test_boot_check_vectors();
+    test_boot_decide();
+    test_boot_corpus();
+}
@@ -0,0 +1,74 @@
+#include "test.h"
+#include "crc32.h"
+#include "sha256.h"
+#include <string.h>
+
+static bool digest_is(const uint8_t digest[SHA256_DIGEST_SIZE], const char *hex)
+{
+    char text[2 * SHA256_DIGEST_SIZE + 1];
+
+    for (int i = 0; i < SHA256_DIGEST_SIZE; i++) {
+        snprintf(&text[2 * i], 3, "%02x", digest[i]);
+    }
+
+    return strcmp(text, hex) == 0;
+}
+
+static void sha256_of(const char *text, uint8_t digest[SHA256_DIGEST_SIZE])
+{
+    Sha256_T sha;
+
+    sha.update((const uint8_t *)text, strlen(text));
+    sha.finalize(digest);
+}
+
+static void test_crc32(void)
+{
+    const uint8_t check[] = "123456789";
+    Crc32_T crc;
+
+    crc.update(check, 9);
+    CHECK(crc.finalize() == 0xCBF43926);
+
+    /* fed in pieces, as the uploads arrive */
+    crc.reset();
+    crc.update(check, 4);
+    crc.update(check + 4, 5);
+    CHECK(crc.finalize() == 0xCBF43926);
+
+    crc.reset();
+    CHECK(crc.finalize() == 0);
+}
+
+static void test_sha256(void)
+{
+    uint8_t digest[SHA256_DIGEST_SIZE];
+
+    sha256_of("", digest);
+    CHECK(digest_is(digest, "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"));
+
+    sha256_of("abc", digest);
+    CHECK(digest_is(digest, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"));
+
+    /* the padding spills into a second block */
+    sha256_of("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq", digest);
+    CHECK(digest_is(digest, "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"));
+
+    Sha256_T sha;
+    uint8_t a[1000];
+
+    /* a million times 'a', in pieces that don't line up with the blocks */
+    memset(a, 'a', sizeof(a));
+    for (int i = 0; i < 1000; i++) {
+        sha.update(a, 7);
+        sha.update(a + 7, sizeof(a) - 7);
+    }
+    sha.finalize(digest);
+    CHECK(digest_is(digest, "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"));
+}
+
+void test_crypto(void)
+{
+    test_crc32();
+    test_sha256();
+}
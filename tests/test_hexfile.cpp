@@ -0,0 +1,81 @@
+#include "test.h"
+#include "hexfile.h"
+
+static void test_ihex(void)
+{
+    Hex_Parser_T parser;
+    Hex_Record_T record;
+
+    CHECK(parser.parse(":0401000001020304F1", &record) == ERR_OK);
+    CHECK(record.type == HEX_RECORD_DATA);
+    CHECK(record.address == 0x0100);
+    CHECK(record.len == 4);
+    CHECK(record.data[0] == 1 && record.data[3] == 4);
+
+    /* extended linear address, kept for the records that follow */
+    CHECK(parser.parse(":0200000490006A", &record) == ERR_OK);
+    CHECK(record.type == HEX_RECORD_NONE);
+    CHECK(parser.parse(":0401000001020304F1", &record) == ERR_OK);
+    CHECK(record.address == 0x90000100);
+
+    /* extended segment address */
+    CHECK(parser.parse(":020000021234B6", &record) == ERR_OK);
+    CHECK(parser.parse(":0401000001020304F1", &record) == ERR_OK);
+    CHECK(record.address == 0x12440);
+
+    parser.reset();
+    CHECK(parser.parse(":0401000001020304F1", &record) == ERR_OK);
+    CHECK(record.address == 0x0100);
+
+    CHECK(parser.parse(":00000001FF", &record) == ERR_OK);
+    CHECK(record.type == HEX_RECORD_END);
+
+    /* trailing whitespace from the terminal */
+    CHECK(parser.parse("  :00000001FF \t", &record) == ERR_OK);
+    CHECK(record.type == HEX_RECORD_END);
+}
+
+static void test_ihex_errors(void)
+{
+    Hex_Parser_T parser;
+    Hex_Record_T record;
+
+    CHECK(parser.parse(":0401000001020304F2", &record) == ERR_HEX_CHECKSUM);
+    CHECK(parser.parse(":0401000001020304F", &record) == ERR_HEX_SYNTAX);
+    CHECK(parser.parse(":0501000001020304F0", &record) == ERR_HEX_SYNTAX);
+    CHECK(parser.parse(":04010000010203G4F1", &record) == ERR_HEX_SYNTAX);
+    CHECK(parser.parse(":00000006FA", &record) == ERR_HEX_RECORD);
+    CHECK(parser.parse(":01000004FFFC", &record) == ERR_HEX_RECORD);
+    CHECK(parser.parse("", &record) == ERR_HEX_SYNTAX);
+    CHECK(parser.parse("hello", &record) == ERR_HEX_SYNTAX);
+}
+
+static void test_srec(void)
+{
+    Hex_Parser_T parser;
+    Hex_Record_T record;
+
+    CHECK(parser.parse("S30990000010DEADBEEF1E", &record) == ERR_OK);
+    CHECK(record.type == HEX_RECORD_DATA);
+    CHECK(record.address == 0x90000010);
+    CHECK(record.len == 4);
+    CHECK(record.data[0] == 0xDE && record.data[3] == 0xEF);
+
+    CHECK(parser.parse("S1040020AA31", &record) == ERR_OK);
+    CHECK(record.address == 0x0020);
+    CHECK(record.len == 1);
+
+    CHECK(parser.parse("S7059000040066", &record) == ERR_OK);
+    CHECK(record.type == HEX_RECORD_END);
+
+    CHECK(parser.parse("S30990000010DEADBEEF1F", &record) == ERR_HEX_CHECKSUM);
+    CHECK(parser.parse("S40990000010DEADBEEF1E", &record) == ERR_HEX_RECORD);
+    CHECK(parser.parse("S30A90000010DEADBEEF1E", &record) == ERR_HEX_SYNTAX);
+}
+
+void test_hexfile(void)
+{
+    test_ihex();
+    test_ihex_errors();
+    test_srec();
+}
@@ -0,0 +1,187 @@
+#include "test.h"
+#include "image_header.h"
+#include "layout.h"
+#include "crc32.h"
+#include <string.h>
+
+static void header_seal(Image_Header_T *header)
+{
+    uint8_t raw[IMAGE_HEADER_SIZE];
+    Crc32_T crc;
+
+    image_header_encode(raw, header);
+    crc.update(raw, offsetof(Image_Header_T, header_crc));
+    header->header_crc = crc.finalize();
+}
+
+static Image_Header_T header_sealed(uint32_t length, uint32_t entry_offset)
+{
+    Image_Header_T header = {};
+
+    header.magic = IMAGE_HEADER_MAGIC;
+    header.version = 7;
+    header.length = length;
+    header.entry_offset = entry_offset;
+    header_seal(&header);
+    return header;
+}
+
+static void test_image_header_codec(void)
+{
+    const uint8_t raw[IMAGE_HEADER_SIZE] = {
+        0x49, 0x41, 0x4D, 0x49, 0x01, 0x02, 0x03, 0x04, 0x00, 0x14, 0x00, 0x00,
+        0x00, 0x04, 0x00, 0x00, 0x50, 0x07, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x80, 0x00, 0x00, 0xC0, 0x0F, 0x6A, 0x10, 0x00, 0x00, 0x00,
+        0x78, 0x56, 0x34, 0x12, 0xEF, 0xBE, 0xAD, 0xDE,
+    };
+    uint8_t again[IMAGE_HEADER_SIZE];
+    Image_Header_T header;
+
+    image_header_decode(&header, raw);
+    CHECK(header.magic == IMAGE_HEADER_MAGIC);
+    CHECK(header.version == 0x04030201);
+    CHECK(header.length == 0x1400);
+    CHECK(header.entry_offset == 0x400);
+    CHECK(header.board == 0x750);
+    CHECK(header.board_rev == 2);
+    CHECK(header.flash_size == 0x800000);
+    CHECK(header.expiry == 0x6A0FC000);
+    CHECK(header.tlv_len == 0x10);
+    CHECK(header.tlv_crc == 0x12345678);
+    CHECK(header.header_crc == 0xDEADBEEF);
+
+    image_header_encode(again, &header);
+    CHECK(memcmp(raw, again, sizeof(raw)) == 0);
+}
+
+static void test_image_header_check(void)
+{
+    Image_Header_T header = header_sealed(0x1400, IMAGE_ENTRY_ALIGN);
+
+    CHECK(image_header_check(&header) == ERR_OK);
+
+    header.magic ^= 1;
+    CHECK(image_header_check(&header) == ERR_IMAGE_MAGIC);
+
+    header = header_sealed(0x1400, IMAGE_ENTRY_ALIGN);
+    header.version++;
+    CHECK(image_header_check(&header) == ERR_IMAGE_HEADER_CRC);
+
+    header = header_sealed(Primary_Slot_T::len + 1, IMAGE_ENTRY_ALIGN);
+    CHECK(image_header_check(&header) == ERR_IMAGE_LENGTH);
+
+    header = header_sealed(Primary_Slot_T::len, IMAGE_ENTRY_ALIGN);
+    CHECK(image_header_check(&header) == ERR_OK);
+
+    header = header_sealed(0x1400, 0);
+    CHECK(image_header_check(&header) == ERR_IMAGE_ENTRY);
+
+    header = header_sealed(0x1400, IMAGE_ENTRY_ALIGN + 4);
+    CHECK(image_header_check(&header) == ERR_IMAGE_ENTRY);
+
+    /* no room left for the stack pointer and the reset vector */
+    header = header_sealed(IMAGE_ENTRY_ALIGN + 4, IMAGE_ENTRY_ALIGN);
+    CHECK(image_header_check(&header) == ERR_IMAGE_ENTRY);
+
+    header = header_sealed(IMAGE_ENTRY_ALIGN + 8, IMAGE_ENTRY_ALIGN);
+    CHECK(image_header_check(&header) == ERR_OK);
+
+    header.tlv_len = IMAGE_TLV_MAX;
+    header_seal(&header);
+    CHECK(image_header_check(&header) == ERR_OK);
+
+    header.tlv_len = IMAGE_TLV_MAX + 1;
+    header_seal(&header);
+    CHECK(image_header_check(&header) == ERR_IMAGE_TLV);
+}
+
+static void test_image_header_match(void)
+{
+    Image_Target_T target = {0x750, 2, 0x800000, 0};
+    Image_Header_T header = header_sealed(0x1400, IMAGE_ENTRY_ALIGN);
+
+    CHECK(image_header_match(&header, &target) == ERR_OK);
+
+    header.board = 0x750;
+    header.board_rev = 2;
+    header.flash_size = 0x800000;
+    CHECK(image_header_match(&header, &target) == ERR_OK);
+
+    header.board = 0x751;
+    CHECK(image_header_match(&header, &target) == ERR_IMAGE_BOARD);
+
+    header.board = 0x750;
+    header.board_rev = 3;
+    CHECK(image_header_match(&header, &target) == ERR_IMAGE_BOARD);
+
+    header.board_rev = 1;
+    header.flash_size = 0x1000000;
+    CHECK(image_header_match(&header, &target) == ERR_IMAGE_FLASH_SIZE);
+
+    header.flash_size = 0;
+    header.expiry = 1800000000;
+    CHECK(image_header_match(&header, &target) == ERR_OK);
+
+    target.now = header.expiry - 1;
+    CHECK(image_header_match(&header, &target) == ERR_OK);
+
+    target.now = header.expiry;
+    CHECK(image_header_match(&header, &target) == ERR_IMAGE_EXPIRED);
+
+    header.expiry = 0;
+    CHECK(image_header_match(&header, &target) == ERR_OK);
+}
+
+static void test_image_tlv(void)
+{
+    const uint8_t area[] = {
+        IMAGE_TLV_BUILD_ID, 3, '1', '.', '2',
+        IMAGE_TLV_USER, 0,
+        IMAGE_TLV_GIT_HASH, 4, 0xDE, 0xAD, 0xBE, 0xEF,
+    };
+    Image_Header_T header = header_sealed(0x1400, IMAGE_ENTRY_ALIGN);
+    Image_Tlv_T tlv;
+    uint32_t offset = 0;
+    Crc32_T crc;
+
+    CHECK(image_tlv_next(area, sizeof(area), &offset, &tlv));
+    CHECK(tlv.tag == IMAGE_TLV_BUILD_ID && tlv.len == 3 && memcmp(tlv.value, "1.2", 3) == 0);
+    CHECK(image_tlv_next(area, sizeof(area), &offset, &tlv));
+    CHECK(tlv.tag == IMAGE_TLV_USER && tlv.len == 0);
+    CHECK(image_tlv_next(area, sizeof(area), &offset, &tlv));
+    CHECK(tlv.tag == IMAGE_TLV_GIT_HASH && tlv.len == 4 && tlv.value == &area[9]);
+    CHECK(!image_tlv_next(area, sizeof(area), &offset, &tlv));
+    CHECK(offset == sizeof(area));
+
+    /* the last entry is cut short */
+    offset = 7;
+    CHECK(!image_tlv_next(area, sizeof(area) - 1, &offset, &tlv));
+    CHECK(offset == 7);
+
+    crc.update(area, sizeof(area));
+    header.tlv_len = sizeof(area);
+    header.tlv_crc = crc.finalize();
+    CHECK(image_tlv_check(&header, area) == ERR_OK);
+
+    header.tlv_crc ^= 1;
+    CHECK(image_tlv_check(&header, area) == ERR_IMAGE_TLV);
+
+    crc.reset();
+    crc.update(area, sizeof(area) - 1);
+    header.tlv_len = sizeof(area) - 1;
+    header.tlv_crc = crc.finalize();
+    CHECK(image_tlv_check(&header, area) == ERR_IMAGE_TLV);
+
+    /* an image without metadata */
+    header.tlv_len = 0;
+    header.tlv_crc = 0;
+    CHECK(image_tlv_check(&header, area) == ERR_OK);
+}
+
+void test_image_header(void)
+{
+    test_image_header_codec();
+    test_image_header_check();
+    test_image_header_match();
+    test_image_tlv();
+}
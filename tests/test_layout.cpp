@@ -0,0 +1,27 @@
+#include "test.h"
+#include "layout.h"
+
+typedef Region_T<0x1000, 0x2000> Test_Region_T;
+
+static_assert(Test_Region_T::end == 0x3000, "region end");
+static_assert(regions_overlap<Test_Region_T, Region_T<0x2000, 0x1000>>(), "regions overlap");
+static_assert(!regions_overlap<Test_Region_T, Region_T<0x3000, 0x1000>>(), "adjacent regions don't overlap");
+
+void test_layout(void)
+{
+    CHECK(Test_Region_T::contains(0x1000, 0x2000));
+    CHECK(Test_Region_T::contains(0x2FFF, 1));
+    CHECK(Test_Region_T::contains(0x3000, 0));
+    CHECK(!Test_Region_T::contains(0x0FFF, 1));
+    CHECK(!Test_Region_T::contains(0x2FFF, 2));
+    CHECK(!Test_Region_T::contains(0x1000, 0xFFFFFFFF));
+
+    CHECK(Test_Region_T::overlaps(0x0FFF, 2));
+    CHECK(Test_Region_T::overlaps(0x2FFF, 0x100));
+    CHECK(!Test_Region_T::overlaps(0x0000, 0x1000));
+    CHECK(!Test_Region_T::overlaps(0x3000, 0x1000));
+    CHECK(!Test_Region_T::overlaps(0x2000, 0));
+
+    CHECK(Metadata_T::overlaps(LAYOUT_METADATA_BASE + LAYOUT_METADATA_LEN - 1, 1));
+    CHECK(!Primary_Slot_T::overlaps(Secondary_Slot_T::base, Secondary_Slot_T::len));
+}
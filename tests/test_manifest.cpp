@@ -0,0 +1,74 @@
+#include "test.h"
+#include "manifest.h"
+#include "w25q.h"
+#include <string.h>
+
+static void test_manifest_ranges(void)
+{
+    Manifest_T manifest = {};
+
+    CHECK(manifest_add_range(&manifest, 0x100, 0x10));
+    CHECK(manifest_add_range(&manifest, 0x110, 0x20));
+    CHECK(manifest.count == 1);
+    CHECK(manifest.ranges[0].offset == 0x100 && manifest.ranges[0].len == 0x30);
+
+    /* a gap or a step back starts a new range */
+    CHECK(manifest_add_range(&manifest, 0x200, 0x10));
+    CHECK(manifest_add_range(&manifest, 0x000, 0x10));
+    CHECK(manifest.count == 3);
+
+    while (manifest.count < MANIFEST_RANGES) {
+        CHECK(manifest_add_range(&manifest, 0x1000 * manifest.count, 1));
+    }
+
+    CHECK(!manifest_add_range(&manifest, 0x100000, 1));
+    CHECK(manifest.count == MANIFEST_RANGES);
+
+    /* merging needs no new range */
+    CHECK(manifest_add_range(&manifest, 0x1000 * (MANIFEST_RANGES - 1) + 1, 1));
+}
+
+static void test_manifest_roundtrip(void)
+{
+    Manifest_T manifest = {};
+    Manifest_T stored;
+
+    memset(flash.memory, 0xFF, sizeof(flash.memory));
+    for (uint32_t i = 0; i < 0x300; i++) {
+        flash.memory[i] = i * 7;
+    }
+
+    CHECK(manifest_add_range(&manifest, 0x000, 0x100));
+    CHECK(manifest_add_range(&manifest, 0x200, 0x100));
+    CHECK(manifest_write(&manifest) == ERR_OK);
+
+    manifest_invalidate();
+    CHECK(manifest_read(&stored) == ERR_OK);
+    CHECK(stored.count == 2);
+    CHECK(manifest_verify(&stored) == ERR_OK);
+
+    /* outside the ranges doesn't count */
+    flash.memory[0x180] = 0;
+    CHECK(manifest_verify(&stored) == ERR_OK);
+
+    flash.memory[0x280] = 0;
+    CHECK(manifest_verify(&stored) == ERR_MANIFEST_HASH);
+
+    flash.fail_reads = true;
+    CHECK(manifest_verify(&stored) == ERR_FLASH_READ);
+    flash.fail_reads = false;
+
+    flash.memory[MANIFEST_OFFSET + offsetof(Manifest_T, count)] ^= 1;
+    manifest_invalidate();
+    CHECK(manifest_read(&stored) == ERR_MANIFEST_INVALID);
+
+    flash.sector_erase(MANIFEST_OFFSET, MANIFEST_OFFSET);
+    manifest_invalidate();
+    CHECK(manifest_read(&stored) == ERR_MANIFEST_INVALID);
+}
+
+void test_manifest(void)
+{
+    test_manifest_ranges();
+    test_manifest_roundtrip();
+}
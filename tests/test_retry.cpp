@@ -0,0 +1,55 @@
+#include "test.h"
+#include "retry.h"
+#include "w25q.h"
+
+struct Script_T {
+    const Error_T *results;
+    uint32_t calls;
+};
+
+/* returns the scripted errors in turn, ERR_OK once they run out */
+static Error_T scripted(void *context)
+{
+    Script_T *script = (Script_T *)context;
+    Error_T error = script->results[script->calls];
+
+    if (error != ERR_OK)
+        script->calls++;
+    return error;
+}
+
+void test_retry(void)
+{
+    const Error_T twice[] = {ERR_FLASH_READ, ERR_FLASH_MISMATCH, ERR_OK};
+    const Error_T permanent[] = {ERR_FLASH_NO_CHIP, ERR_OK};
+    const Error_T forever[] = {ERR_FLASH_WRITE, ERR_FLASH_WRITE, ERR_FLASH_WRITE, ERR_FLASH_WRITE, ERR_OK};
+    uint32_t retries = retry_count();
+
+    Script_T script = {twice, 0};
+    stub_delay_ms = 0;
+    CHECK(retry(&retry_flash, scripted, &script) == ERR_OK);
+    CHECK(script.calls == 2);
+    CHECK(retry_count() == retries + 2);
+    CHECK(stub_delay_ms == RETRY_FLASH_BACKOFF_MS * 3);
+
+    script = {permanent, 0};
+    stub_delay_ms = 0;
+    CHECK(retry(&retry_flash, scripted, &script) == ERR_FLASH_NO_CHIP);
+    CHECK(script.calls == 1);
+    CHECK(stub_delay_ms == 0);
+
+    script = {forever, 0};
+    CHECK(retry(&retry_flash, scripted, &script) == ERR_FLASH_WRITE);
+    CHECK(script.calls == RETRY_FLASH_ATTEMPTS);
+
+    /* the backoff doubles up to the limit */
+    const Retry_Policy_T capped = {5, 10, 25, retry_flash_transient};
+
+    script = {forever, 0};
+    stub_delay_ms = 0;
+    CHECK(retry(&capped, scripted, &script) == ERR_OK);
+    CHECK(stub_delay_ms == 10 + 20 + 25 + 25);
+
+    CHECK(!retry_flash_transient(ERR_OK));
+    CHECK(!retry_flash_transient(ERR_OUT_OF_RANGE));
+}
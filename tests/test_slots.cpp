@@ -0,0 +1,244 @@
+#include "test.h"
+#include "slots.h"
+#include "layout.h"
+#include "image_header.h"
+#include "manifest.h"
+#include "crc32.h"
+#include "w25q.h"
+#include <string.h>
+
+#define OLD_LEN (2 * LAYOUT_SECTOR_SIZE)
+#define NEW_LEN (3 * LAYOUT_SECTOR_SIZE)
+
+static uint8_t old_image[NEW_LEN];
+static uint8_t new_image[NEW_LEN];
+
+/* an image with a valid header, the bytes behind it made up from seed */
+static void image_make(uint8_t *image, uint32_t len, uint8_t seed)
+{
+    Image_Header_T header = {};
+    uint8_t raw[IMAGE_HEADER_SIZE];
+    Crc32_T crc;
+
+    memset(image, 0xFF, NEW_LEN);
+    for (uint32_t i = 0; i < len; i++) {
+        image[i] = seed + i * 13;
+    }
+
+    header.magic = IMAGE_HEADER_MAGIC;
+    header.version = seed;
+    header.length = len;
+    header.entry_offset = IMAGE_ENTRY_ALIGN;
+    image_header_encode(raw, &header);
+    crc.update(raw, offsetof(Image_Header_T, header_crc));
+    header.header_crc = crc.finalize();
+    image_header_encode(image, &header);
+}
+
+/* a board with the old image running and the new one staged and marked */
+static void slots_staged(void)
+{
+    memset(flash.memory, 0xFF, sizeof(flash.memory));
+    flash.cut_after = 0;
+    slot_state_invalidate();
+    manifest_invalidate();
+
+    image_make(old_image, OLD_LEN, 1);
+    image_make(new_image, NEW_LEN, 2);
+    memcpy(&flash.memory[Primary_Slot_T::base], old_image, NEW_LEN);
+    memcpy(&flash.memory[Secondary_Slot_T::base], new_image, NEW_LEN);
+
+    CHECK(slot_state_write(1, NEW_LEN, SLOT_TRIAL_NONE) == ERR_OK);
+}
+
+/* what is left of the bootloader after a reset is what it reads back from the flash */
+static void slots_reset(void)
+{
+    flash.cut_after = 0;
+    slot_state_invalidate();
+    manifest_invalidate();
+}
+
+static uint32_t slots_trial(void)
+{
+    Slot_State_T state;
+
+    CHECK(slot_state_read(&state) == ERR_OK);
+    CHECK(state.pending == SLOT_NONE);
+    return state.trial;
+}
+
+static bool slot_holds(uint32_t base, const uint8_t *image)
+{
+    return memcmp(&flash.memory[base], image, NEW_LEN) == 0;
+}
+
+static void test_slots_install(void)
+{
+    Error_T error;
+    Manifest_T manifest;
+
+    slots_staged();
+    CHECK(slot_update(&error) == SLOT_ACTION_INSTALLED);
+    CHECK(error == ERR_OK);
+    CHECK(slot_holds(Primary_Slot_T::base, new_image));
+    CHECK(slot_holds(Secondary_Slot_T::base, old_image));
+    CHECK(slots_trial() == SLOT_TRIAL_INSTALLED);
+
+    CHECK(manifest_read(&manifest) == ERR_OK);
+    CHECK(manifest.count == 1 && manifest.ranges[0].len == NEW_LEN);
+    CHECK(manifest_verify(&manifest) == ERR_OK);
+
+    /* not started yet, so nothing happens on another boot */
+    slots_reset();
+    CHECK(slot_update(&error) == SLOT_ACTION_NONE);
+    CHECK(slots_trial() == SLOT_TRIAL_INSTALLED);
+
+    CHECK(slot_trial_start() == ERR_OK);
+    CHECK(slots_trial() == SLOT_TRIAL_TESTING);
+    CHECK(slot_confirm() == ERR_OK);
+    CHECK(slots_trial() == SLOT_TRIAL_NONE);
+
+    slots_reset();
+    CHECK(slot_update(&error) == SLOT_ACTION_NONE);
+    CHECK(slot_holds(Primary_Slot_T::base, new_image));
+}
+
+static void test_slots_revert(void)
+{
+    Error_T error;
+
+    slots_staged();
+    CHECK(slot_update(&error) == SLOT_ACTION_INSTALLED);
+    CHECK(slot_trial_start() == ERR_OK);
+
+    /* reset without a confirm */
+    slots_reset();
+    CHECK(slot_update(&error) == SLOT_ACTION_REVERTED);
+    CHECK(error == ERR_OK);
+    CHECK(slot_holds(Primary_Slot_T::base, old_image));
+    CHECK(slot_holds(Secondary_Slot_T::base, new_image));
+    CHECK(slots_trial() == SLOT_TRIAL_NONE);
+
+    /* the image that was pushed out isn't installed again by itself */
+    slots_reset();
+    CHECK(slot_update(&error) == SLOT_ACTION_NONE);
+    CHECK(slot_holds(Primary_Slot_T::base, old_image));
+}
+
+/* the running image pushed again is dropped before anything is erased */
+static void test_slots_same(void)
+{
+    Error_T error;
+
+    slots_staged();
+    memcpy(&flash.memory[Primary_Slot_T::base], new_image, NEW_LEN);
+    flash.ops = 0;
+    flash.cut_after = UINT32_MAX;
+
+    CHECK(slot_update(&error) == SLOT_ACTION_SAME);
+    CHECK(error == ERR_OK);
+    CHECK(slots_trial() == SLOT_TRIAL_NONE);
+    CHECK(slot_holds(Primary_Slot_T::base, new_image));
+    CHECK(slot_holds(Secondary_Slot_T::base, new_image));
+
+    /* only the state sector was erased and written */
+    CHECK(flash.ops == 2);
+
+    /* any byte the header covers counts */
+    slots_staged();
+    memcpy(&flash.memory[Primary_Slot_T::base], new_image, NEW_LEN);
+    flash.memory[Primary_Slot_T::base + NEW_LEN - 1] ^= 1;
+    CHECK(slot_update(&error) == SLOT_ACTION_INSTALLED);
+}
+
+static void test_slots_refused(void)
+{
+    Error_T error;
+
+    slots_staged();
+    flash.memory[Secondary_Slot_T::base] ^= 1;
+    CHECK(slot_update(&error) == SLOT_ACTION_REFUSED);
+    CHECK(error == ERR_IMAGE_MAGIC);
+    CHECK(slot_holds(Primary_Slot_T::base, old_image));
+    CHECK(slots_trial() == SLOT_TRIAL_NONE);
+
+    /* staged past the marked size */
+    slots_staged();
+    CHECK(slot_state_write(1, 2 * LAYOUT_SECTOR_SIZE, SLOT_TRIAL_NONE) == ERR_OK);
+    CHECK(slot_update(&error) == SLOT_ACTION_REFUSED);
+    CHECK(error == ERR_IMAGE_LENGTH);
+    CHECK(slot_holds(Primary_Slot_T::base, old_image));
+}
+
+/* the first image on a board pushes out nothing a revert could go back to */
+static void test_slots_nothing_to_revert(void)
+{
+    Error_T error;
+
+    slots_staged();
+    memset(&flash.memory[Primary_Slot_T::base], 0xFF, NEW_LEN);
+    CHECK(slot_update(&error) == SLOT_ACTION_INSTALLED);
+    CHECK(slot_trial_start() == ERR_OK);
+
+    slots_reset();
+    CHECK(slot_update(&error) == SLOT_ACTION_KEPT);
+    CHECK(error == ERR_IMAGE_MAGIC);
+    CHECK(slot_holds(Primary_Slot_T::base, new_image));
+    CHECK(slots_trial() == SLOT_TRIAL_NONE);
+}
+
+/* cut the power at every erase and write of an install and of a revert in turn */
+static void test_slots_power_cut(void)
+{
+    Error_T error;
+    bool finished = false;
+
+    for (uint32_t cut = 1; !finished; cut++) {
+        slots_staged();
+        flash.ops = 0;
+        flash.cut_after = cut;
+        finished = slot_update(&error) == SLOT_ACTION_INSTALLED;
+
+        /* cut after the state was written, only the swap progress was left to clean up */
+        slots_reset();
+        if (!finished) {
+            Slot_Action_T action = slot_update(&error);
+            CHECK(action == SLOT_ACTION_INSTALLED || action == SLOT_ACTION_NONE);
+        }
+        CHECK(slot_holds(Primary_Slot_T::base, new_image));
+        CHECK(slot_holds(Secondary_Slot_T::base, old_image));
+        CHECK(slots_trial() == SLOT_TRIAL_INSTALLED);
+    }
+
+    finished = false;
+    for (uint32_t cut = 1; !finished; cut++) {
+        slots_staged();
+        CHECK(slot_update(&error) == SLOT_ACTION_INSTALLED);
+        CHECK(slot_trial_start() == ERR_OK);
+
+        slots_reset();
+        flash.ops = 0;
+        flash.cut_after = cut;
+        finished = slot_update(&error) == SLOT_ACTION_REVERTED;
+
+        slots_reset();
+        if (!finished) {
+            Slot_Action_T action = slot_update(&error);
+            CHECK(action == SLOT_ACTION_REVERTED || action == SLOT_ACTION_NONE);
+        }
+        CHECK(slot_holds(Primary_Slot_T::base, old_image));
+        CHECK(slot_holds(Secondary_Slot_T::base, new_image));
+        CHECK(slots_trial() == SLOT_TRIAL_NONE);
+    }
+}
+
+void test_slots(void)
+{
+    test_slots_install();
+    test_slots_revert();
+    test_slots_same();
+    test_slots_refused();
+    test_slots_nothing_to_revert();
+    test_slots_power_cut();
+}
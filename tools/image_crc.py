@@ -0,0 +1,20 @@
+#!/usr/bin/env python3
+# crc32 of a bootloader binary, leaving out its last word where the crc goes.
+# usage: image_crc.py <image.bin> <crc.bin>
+
+import struct
+import sys
+import zlib
+
+with open(sys.argv[1], "rb") as f:
+    image = f.read()
+
+if len(image) < 4 or len(image) % 4:
+    sys.exit("image_crc: %s is not a whole number of words" % sys.argv[1])
+
+crc = zlib.crc32(image[:-4]) & 0xFFFFFFFF
+
+with open(sys.argv[2], "wb") as f:
+    f.write(struct.pack("<I", crc))
+
+print("image crc 0x%08x over %d bytes" % (crc, len(image) - 4))
@@ -0,0 +1,116 @@
+#!/usr/bin/env python3
+# put the image header in front of an application binary linked for the
+# primary slot with its vector table at the entry offset, see src/api/image_header.h.
+# a binary that starts with a header from IMAGE_HEADER() (src/api/app_header.h)
+# is completed in place instead, the options override the fields it set
+# usage: image_header.py <app.bin> <image.bin|image.hex> [version] [entry offset, default 0x400]
+#                        [--board ID] [--board-rev N] [--flash-size BYTES]
+#                        [--expiry YYYY-MM-DD|unix time] [--tlv TAG=VALUE ...]
+# an output ending in .hex is written as intel hex at the start of the window,
+# ready for the console load command
+
+import argparse
+import datetime
+import struct
+import sys
+import zlib
+
+MAGIC = 0x494D4149
+HEADER = "<IIIIIIIIII"
+ENTRY_ALIGN = 0x400
+XIP_BASE = 0x90000000
+TLV_TAGS = {"build-id": 0x01, "git-hash": 0x02, "channel": 0x03, "customer": 0x04}
+
+def expiry_time(text):
+    try:
+        return int(text, 0)
+    except ValueError:
+        date = datetime.datetime.strptime(text, "%Y-%m-%d").replace(tzinfo=datetime.timezone.utc)
+        return int(date.timestamp())
+
+
+# a tag by name or number, 0x80 and up for the application's own. values are text
+def tlv_entry(text):
+    tag, sep, value = text.partition("=")
+    if not sep:
+        raise argparse.ArgumentTypeError("expected TAG=VALUE")
+    tag = TLV_TAGS[tag] if tag in TLV_TAGS else int(tag, 0)
+    value = value.encode()
+    if not 0 < tag < 0x100 or len(value) > 0xFF:
+        raise argparse.ArgumentTypeError("tag out of range or value over 255 bytes")
+    return bytes([tag, len(value)]) + value
+
+
+parser = argparse.ArgumentParser(description="add the bootloader image header to an application")
+parser.add_argument("app")
+parser.add_argument("image")
+parser.add_argument("version", nargs="?", type=lambda text: int(text, 0))
+parser.add_argument("entry", nargs="?", type=lambda text: int(text, 0))
+parser.add_argument("--board", type=lambda text: int(text, 0), help="board id, 0 for any")
+parser.add_argument("--board-rev", type=lambda text: int(text, 0), help="lowest board revision")
+parser.add_argument("--flash-size", type=lambda text: int(text, 0),
+                    help="external flash the image needs at least, in bytes")
+parser.add_argument("--expiry", type=expiry_time,
+                    help="utc date or unix time a production locked board stops starting the image at")
+parser.add_argument("--tlv", type=tlv_entry, action="append", default=[], metavar="TAG=VALUE",
+                    help="metadata entry, tag one of %s or a number" % ", ".join(TLV_TAGS))
+args = parser.parse_args()
+
+with open(args.app, "rb") as f:
+    app = f.read()
+
+tlv = b"".join(args.tlv)
+header_size = struct.calcsize(HEADER) + 4
+embedded = len(app) >= header_size and struct.unpack_from("<I", app)[0] == MAGIC
+
+# magic, version, length, entry offset, board, board rev, flash size, expiry
+fields = list(struct.unpack_from("<IIIIIIII", app)) if embedded else [MAGIC, None, 0, 0, 0, 0, 0, 0]
+for index, value in ((1, args.version), (3, args.entry), (4, args.board), (5, args.board_rev),
+                     (6, args.flash_size), (7, args.expiry)):
+    if value is not None:
+        fields[index] = value
+
+if fields[1] is None:
+    sys.exit("image_header: no version given and no header built into %s" % args.app)
+
+entry = fields[3] or ENTRY_ALIGN
+
+if len(tlv) > ENTRY_ALIGN - header_size:
+    sys.exit("image_header: %d bytes of metadata, at most %d fit" % (len(tlv), ENTRY_ALIGN - header_size))
+if entry < header_size + len(tlv) or entry % ENTRY_ALIGN:
+    sys.exit("image_header: entry offset 0x%x is not a multiple of 0x%x past the header" % (entry, ENTRY_ALIGN))
+
+# the binary already starts at the slot, the linker left the gap up to the vectors
+if embedded:
+    if len(app) < entry + 8:
+        sys.exit("image_header: %s ends before its vector table at 0x%x" % (args.app, entry))
+    if app[header_size:entry].strip(b"\x00").strip(b"\xff"):
+        sys.exit("image_header: something is linked between the header and the vector table")
+    app = app[entry:]
+
+fields[2] = entry + len(app)
+fields[3] = entry
+fields = struct.pack(HEADER, *fields, len(tlv), zlib.crc32(tlv) & 0xFFFFFFFF)
+header = fields + struct.pack("<I", zlib.crc32(fields) & 0xFFFFFFFF)
+
+image = header + tlv + b"\xff" * (entry - len(header) - len(tlv)) + app
+
+
+def hex_record(kind, address, data):
+    record = bytes([len(data), address >> 8, address & 0xFF, kind]) + data
+    return ":%s%02X\n" % (record.hex().upper(), -sum(record) & 0xFF)
+
+
+if args.image.endswith(".hex"):
+    with open(args.image, "w") as f:
+        for offset in range(0, len(image), 16):
+            address = XIP_BASE + offset
+            if offset == 0 or address & 0xFFFF == 0:
+                f.write(hex_record(4, 0, struct.pack(">H", address >> 16)))
+            f.write(hex_record(0, address & 0xFFFF, image[offset:offset + 16]))
+        f.write(hex_record(1, 0, b""))
+else:
+    with open(args.image, "wb") as f:
+        f.write(image)
+
+print("image version %d, %d bytes, vector table at 0x%x" % (struct.unpack_from("<I", fields, 4)[0], len(image), entry))